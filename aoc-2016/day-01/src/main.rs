@@ -1,18 +1,18 @@
 use anyhow::{bail, Result};
 use num_complex::Complex;
-use std::collections::HashSet;
-use std::fs;
+use std::{env, fs};
+use walker::Walker;
 
 struct Instruction {
     dir: Complex<i32>, // better to use enum?
-    len: i32,
+    len: u32,
 }
 
 fn parse_instructions(input: Vec<String>) -> Result<Vec<Instruction>> {
     let mut ins: Vec<Instruction> = Vec::with_capacity(input.len());
 
     for string in input {
-        let len: i32 = string[1..].parse()?;
+        let len: u32 = string[1..].parse()?;
         let dir: Complex<i32> = match string.chars().next() {
             Some('L') => Complex::new(0, 1),
             Some('R') => Complex::new(0, -1),
@@ -26,42 +26,17 @@ fn parse_instructions(input: Vec<String>) -> Result<Vec<Instruction>> {
     Ok(ins)
 }
 
-fn compute_destination(instructions: &Vec<Instruction>) -> (i32, i32) {
-    let mut pos = Complex::new(0, 0); // starting position
+/// Follow the instructions from the origin, facing north, and record the whole path.
+fn walk_instructions(instructions: &[Instruction]) -> Walker {
+    let mut walker = Walker::new(Complex::new(0, 0)); // starting position
     let mut dir = Complex::new(0, 1); // starting direction
 
     for ins in instructions {
-        dir = dir * ins.dir;
-        pos = pos + ins.len * dir;
+        dir *= ins.dir;
+        walker.walk(dir, ins.len);
     }
 
-    (pos.re.abs(), pos.im.abs())
-}
-
-fn first_visited_twice(instructions: &Vec<Instruction>) -> Option<(i32, i32)> {
-    let mut pos = Complex::new(0, 0); // starting position
-    let mut dir = Complex::new(0, 1); // starting direction
-
-    // build a set to contain all visited positions
-    let mut set: HashSet<Complex<i32>> = HashSet::new();
-    set.insert(pos); // insert starting position
-
-    for ins in instructions {
-        dir = dir * ins.dir;
-
-        // walk one step at the time and add all visited positions to the set
-        for _ in 1..=ins.len {
-            pos = pos + dir;
-
-            if set.contains(&pos) {
-                return Some((pos.re.abs(), pos.im.abs()));
-            }
-
-            set.insert(pos);
-        }
-    }
-
-    None
+    walker
 }
 
 fn main() -> Result<()> {
@@ -73,15 +48,22 @@ fn main() -> Result<()> {
 
     let ins = parse_instructions(input)?;
 
-    let dest = compute_destination(&ins);
-    let part_1 = dest.0 + dest.1;
+    let walker = walk_instructions(&ins);
+
+    let part_1 = walker.position().l1_norm();
 
     dbg!(part_1);
 
-    let fwt = first_visited_twice(&ins).expect("no position visited twice");
-    let part_2 = fwt.0 + fwt.1;
+    let fwt = walker.first_revisit().expect("no position visited twice");
+    let part_2 = fwt.l1_norm();
 
     dbg!(part_2);
 
+    // the path is drawn, and written as SVG to the path given as argument, if any
+    if let Some(path) = env::args().nth(1) {
+        println!("{}", walker.render_ascii());
+        fs::write(path, walker.render_svg())?;
+    }
+
     Ok(())
 }
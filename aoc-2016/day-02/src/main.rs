@@ -1,76 +1,134 @@
-use anyhow::Result;
-use num_complex::Complex;
+use anyhow::{anyhow, bail, Result};
 use std::fs;
 
-// Represent the keypad as a disc K = {|s|^2 < r} in the complex plane chosen so that
-// the integer points inside K correspond to the keys.
-// A move on the keypad is valid as long as the destination remains in K.
-//
-// for part 1: 2 < r < 4
-// for part 2: 4 < r < 5
-
-type Position = Complex<i8>;
-
-const UP: Complex<i8> = Complex::new(0, 1);
-const DOWN: Complex<i8> = Complex::new(0, -1);
-const LEFT: Complex<i8> = Complex::new(-1, 0);
-const RIGHT: Complex<i8> = Complex::new(1, 0);
-
-/// Take a single step starting from position `pos` in direction `dir`
-/// on a keypad with squared radius `rad_sqr`.
-fn step(rad_sqr: f32, pos: Position, dir: char) -> Position {
-    let step = match dir {
-        'U' => UP,
-        'D' => DOWN,
-        'L' => LEFT,
-        'R' => RIGHT,
-        _ => panic!("unexpected direction: {dir}"),
-    };
-
-    let dest = pos + step;
-
-    // if the destination is outside the keypad, return the starting position
-    if dest.norm_sqr() as f32 >= rad_sqr {
-        return pos;
-    }
+// Represent the keypad as a rectangular grid of optional keys, read from an ASCII layout
+// in which blanks mark the missing keys.
+// A move on the keypad is valid as long as the destination is a key.
+
+const KEYPAD_1: &str = "\
+123
+456
+789";
+
+const KEYPAD_2: &str = "  1
+ 234
+56789
+ ABC
+  D";
 
-    dest
+type Position = (usize, usize); // (row, col), top-to-bottom and left-to-right
+
+struct Keypad {
+    keys: Vec<Vec<Option<char>>>,
 }
 
-fn walk(rad_sqr: f32, ins: &str) -> Position {
-    let mut pos: Complex<i8> = Complex::new(0, 0);
+impl Keypad {
+    /// Parse a keypad from its ASCII layout.
+    /// Shorter lines are padded with blanks so that the keypad is rectangular.
+    fn parse(layout: &str) -> Result<Self> {
+        let width = layout.lines().map(|line| line.chars().count()).max();
+
+        let Some(width) = width.filter(|&width| width > 0) else {
+            bail!("empty keypad layout");
+        };
+
+        let keys: Vec<Vec<Option<char>>> = layout
+            .lines()
+            .map(|line| {
+                let mut row: Vec<Option<char>> = line
+                    .chars()
+                    .map(|ch| Some(ch).filter(|ch| *ch != ' '))
+                    .collect();
+                row.resize(width, None);
+                row
+            })
+            .collect();
+
+        Ok(Self { keys })
+    }
 
-    for dir in ins.chars() {
-        pos = step(rad_sqr, pos, dir);
+    /// Return the key at position `pos`, if any.
+    fn key(&self, pos: Position) -> Option<char> {
+        *self.keys.get(pos.0)?.get(pos.1)?
     }
 
-    pos
-}
+    /// Return the position of the key labelled `key`.
+    fn find(&self, key: char) -> Option<Position> {
+        self.keys.iter().enumerate().find_map(|(row, keys)| {
+            let col = keys.iter().position(|&k| k == Some(key))?;
+            Some((row, col))
+        })
+    }
 
-/// Converts a position on the keypad from part 1 to the corresponding character
-/// NOTE: did not need to shift, can start from center
-fn convert_1(pos: Complex<i8>) -> char {
-    let row = (1 - pos.im) as u32; // top-to-bottom, start at 0
-    let col = 2 - (1 - pos.re) as u32; // left-to-right, start at 0
+    /// Take a single step starting from position `pos` in direction `dir`.
+    fn step(&self, pos: Position, dir: char) -> Result<Position> {
+        let dest = match dir {
+            'U' => (pos.0.wrapping_sub(1), pos.1),
+            'D' => (pos.0 + 1, pos.1),
+            'L' => (pos.0, pos.1.wrapping_sub(1)),
+            'R' => (pos.0, pos.1 + 1),
+            _ => bail!("unexpected direction: {dir}"),
+        };
+
+        // if the destination is not a key, stay on the starting position
+        match self.key(dest) {
+            Some(_) => Ok(dest),
+            None => Ok(pos),
+        }
+    }
 
-    let num = 1 + 3 * row + col;
+    /// Follow the instructions line by line starting from the key `start`.
+    /// Return every position visited, starting position included.
+    fn trace(&self, start: char, input: &[String]) -> Result<Vec<Position>> {
+        let mut pos = self
+            .find(start)
+            .ok_or(anyhow!("no key {start} on keypad"))?;
+        let mut path = vec![pos];
 
-    char::from_digit(num, 10).expect("failed to convert {num} to char")
-}
+        for dir in input.iter().flat_map(|ins| ins.chars()) {
+            pos = self.step(pos, dir)?;
+            path.push(pos);
+        }
 
-/// Converts a position on the keypad from part 2 to the corresponding character
-fn convert_2(pos: Complex<i8>) -> char {
-    let row_start: [i32; 5] = [1, 2, 5, 10, 13];
-    let row_shift: [i32; 5] = [0, 1, 2, 1, 0]; // length left and right of center
+        Ok(path)
+    }
+
+    /// Compute the code obtained by following the instructions from the key `start`.
+    /// Each line of instructions starts where the previous one ended.
+    fn code(&self, start: char, input: &[String]) -> Result<String> {
+        let path = self.trace(start, input)?;
 
-    let row = (2 - pos.im) as u32;
+        // index in the path of the position reached at the end of each line
+        let ends = input.iter().scan(0, |steps, ins| {
+            *steps += ins.chars().count();
+            Some(*steps)
+        });
 
-    // compute the column relative to the row
-    let col = (pos.re as i32) + row_shift[row as usize];
+        Ok(ends
+            .map(|end| self.key(path[end]).expect("walked off the keypad"))
+            .collect())
+    }
 
-    let num = row_start[row as usize] + col;
+    /// Render the keypad with the keys along `path` in brackets.
+    fn render_path(&self, path: &[Position]) -> String {
+        let mut render = String::new();
 
-    char::from_digit(num as u32, 14).expect("failed to convert {num} to char")
+        for (row, keys) in self.keys.iter().enumerate() {
+            for (col, key) in keys.iter().enumerate() {
+                let cell = match key {
+                    Some(key) if path.contains(&(row, col)) => format!("[{key}]"),
+                    Some(key) => format!(" {key} "),
+                    None => String::from("   "),
+                };
+
+                render.push_str(&cell);
+            }
+
+            render.push('\n');
+        }
+
+        render
+    }
 }
 
 fn main() -> Result<()> {
@@ -79,23 +137,109 @@ fn main() -> Result<()> {
         .map(String::from)
         .collect();
 
-    let part_1: String = input
-        .iter()
-        .map(|ins| walk(3.0, ins))
-        .map(|pos| convert_1(pos))
-        .collect();
+    let keypad_1 = Keypad::parse(KEYPAD_1)?;
+    let part_1 = keypad_1.code('5', &input)?;
 
     dbg!(part_1);
 
-    let part_2: String = input
-        .iter()
-        .map(|ins| walk(4.5, ins))
-        .map(|pos| convert_2(pos))
-        .collect();
-
-    let part_2 = part_2.to_uppercase();
+    let keypad_2 = Keypad::parse(KEYPAD_2)?;
+    let part_2 = keypad_2.code('5', &input)?;
 
     dbg!(part_2);
 
+    // the path on the second keypad is drawn if asked for
+    if std::env::args().nth(1).as_deref() == Some("--render") {
+        let path = keypad_2.trace('5', &input)?;
+        println!("{}", keypad_2.render_path(&path));
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(instructions: &str) -> Vec<String> {
+        instructions.lines().map(String::from).collect()
+    }
+
+    const EXAMPLE: &str = "ULL\nRRDDD\nLURDL\nUUUUD";
+
+    #[test]
+    fn parse() {
+        let keypad = Keypad::parse(KEYPAD_2).unwrap();
+
+        // the short lines are padded with blanks
+        assert!(keypad.keys.iter().all(|row| row.len() == 5));
+        assert_eq!(Some('1'), keypad.key((0, 2)));
+        assert_eq!(None, keypad.key((0, 0)));
+        assert_eq!(None, keypad.key((4, 4)));
+        assert_eq!(None, keypad.key((5, 0)));
+        assert_eq!(Some((2, 0)), keypad.find('5'));
+
+        assert!(Keypad::parse("").is_err());
+    }
+
+    #[test]
+    fn step() {
+        let keypad = Keypad::parse(KEYPAD_1).unwrap();
+
+        assert_eq!((0, 1), keypad.step((1, 1), 'U').unwrap());
+        assert_eq!((1, 2), keypad.step((1, 1), 'R').unwrap());
+
+        // moves off the keypad are ignored
+        assert_eq!((0, 0), keypad.step((0, 0), 'U').unwrap());
+        assert_eq!((0, 0), keypad.step((0, 0), 'L').unwrap());
+        assert_eq!((2, 2), keypad.step((2, 2), 'D').unwrap());
+
+        assert!(keypad.step((1, 1), 'X').is_err());
+
+        // and so are the moves to a blank
+        let keypad = Keypad::parse(KEYPAD_2).unwrap();
+        assert_eq!((2, 0), keypad.step((2, 0), 'U').unwrap());
+    }
+
+    #[test]
+    fn trace() {
+        let keypad = Keypad::parse(KEYPAD_1).unwrap();
+        let path = keypad.trace('5', &input(EXAMPLE)).unwrap();
+
+        // one position per instruction, after the starting one
+        assert_eq!(1 + 3 + 5 + 5 + 5, path.len());
+        assert_eq!(vec![(1, 1), (0, 1), (0, 0), (0, 0)], path[..4]);
+        assert_eq!((1, 1), *path.last().unwrap()); // the last key is 5
+
+        assert!(keypad.trace('A', &input(EXAMPLE)).is_err());
+    }
+
+    #[test]
+    fn code() {
+        let keypad_1 = Keypad::parse(KEYPAD_1).unwrap();
+        assert_eq!("1985", keypad_1.code('5', &input(EXAMPLE)).unwrap());
+
+        let keypad_2 = Keypad::parse(KEYPAD_2).unwrap();
+        assert_eq!("5DB3", keypad_2.code('5', &input(EXAMPLE)).unwrap());
+    }
+
+    #[test]
+    fn render_path() {
+        let keypad = Keypad::parse(KEYPAD_1).unwrap();
+        let path = keypad.trace('5', &input("ULL")).unwrap();
+
+        let expected = "[1][2] 3 \n 4 [5] 6 \n 7  8  9 \n";
+        assert_eq!(expected, keypad.render_path(&path));
+
+        let keypad = Keypad::parse(KEYPAD_2).unwrap();
+        let path = keypad.trace('5', &input("R")).unwrap();
+
+        assert_eq!(
+            "       1       ",
+            keypad.render_path(&path).lines().next().unwrap()
+        );
+        assert_eq!(
+            "[5][6] 7  8  9 ",
+            keypad.render_path(&path).lines().nth(2).unwrap()
+        );
+    }
+}
@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Result};
+use regex::Regex;
 use std::collections::HashMap;
 
-struct Room {
+pub struct Room {
     name: Vec<String>,
     sector_id: u32,
     checksum: String,
@@ -36,18 +37,18 @@ impl Room {
         })
     }
 
-    pub fn is_real(&self) -> bool {
-        // fold the name Vector into a String
-        let name: String = self.name.iter().fold(String::new(), |mut name, s| {
-            name.push_str(s);
-            name
-        });
-
+    /// Computes the checksum of the room name.
+    /// The checksum is made of the 5 most common letters, ties broken alphabetically.
+    pub fn expected_checksum(&self) -> String {
         // populate a HashMap with the character count for the name (could also use a for-loop)
-        let char_count: HashMap<char, i32> = name.chars().fold(HashMap::new(), |mut map, ch| {
-            *map.entry(ch).or_insert(0) += 1;
-            map
-        });
+        let char_count: HashMap<char, i32> =
+            self.name
+                .iter()
+                .flat_map(|s| s.chars())
+                .fold(HashMap::new(), |mut map, ch| {
+                    *map.entry(ch).or_insert(0) += 1;
+                    map
+                });
 
         // map the tuples: (char, count) -> (-count, char)
         // sorting the resulting vector (in ascending lexicographic order) puts the most
@@ -60,9 +61,11 @@ impl Room {
         char_count.sort(); // sorting gives the correct ordering
 
         // collect the 5 most common characters into a String
-        let top_5: String = char_count.into_iter().take(5).map(|(_, ch)| ch).collect();
+        char_count.into_iter().take(5).map(|(_, ch)| ch).collect()
+    }
 
-        top_5 == self.checksum
+    pub fn is_real(&self) -> bool {
+        self.expected_checksum() == self.checksum
     }
 
     /// Decrypts a single word of the room name (a vector of words).
@@ -70,7 +73,7 @@ impl Room {
         let shift: u8 = (self.sector_id % 26) as u8;
 
         word.chars()
-            .map(|ch| (((ch as u8) - ('a' as u8) + shift) % 26 + ('a' as u8)) as char)
+            .map(|ch| ((ch as u8 - b'a' + shift) % 26 + b'a') as char)
             .collect::<String>()
     }
 
@@ -87,17 +90,115 @@ impl Room {
 }
 
 /// Filter the input to retain only the real rooms.
-fn filter_real_rooms(input: &Vec<String>) -> Result<Vec<Room>> {
+fn filter_real_rooms(input: &[String]) -> Result<Vec<Room>> {
     let mut rooms: Vec<Room> = Vec::with_capacity(input.len());
 
     for line in input {
-        let room = Room::from_line(&line)?;
+        let room = Room::from_line(line)?;
         rooms.push(room);
     }
 
     Ok(rooms.into_iter().filter(|room| room.is_real()).collect())
 }
 
+/// A room whose checksum does not match its name.
+pub struct Mismatch<'a> {
+    pub room: &'a Room,
+    pub expected: String, // checksum computed from the name
+}
+
+/// Index of the rooms together with their decrypted names.
+/// Queries over the names only return real rooms.
+pub struct RoomIndex {
+    rooms: Vec<(Room, String)>, // (room, decrypted name)
+}
+
+impl RoomIndex {
+    pub fn from_input(input: &[String]) -> Result<Self> {
+        let mut rooms: Vec<(Room, String)> = Vec::with_capacity(input.len());
+
+        for line in input {
+            let room = Room::from_line(line)?;
+            let name = room.decrypt();
+            rooms.push((room, name));
+        }
+
+        Ok(Self { rooms })
+    }
+
+    /// Iterates over the real rooms and their decrypted names.
+    pub fn real_rooms(&self) -> impl Iterator<Item = (&Room, &str)> {
+        self.rooms
+            .iter()
+            .filter(|(room, _)| room.is_real())
+            .map(|(room, name)| (room, name.as_str()))
+    }
+
+    /// Finds the real rooms whose decrypted name contains `pattern`.
+    pub fn find(&self, pattern: &str) -> Vec<(&Room, &str)> {
+        self.real_rooms()
+            .filter(|(_, name)| name.contains(pattern))
+            .collect()
+    }
+
+    /// Finds the real rooms whose decrypted name matches the regular expression `pattern`.
+    pub fn find_regex(&self, pattern: &str) -> Result<Vec<(&Room, &str)>> {
+        let regex = Regex::new(pattern)?;
+
+        Ok(self
+            .real_rooms()
+            .filter(|(_, name)| regex.is_match(name))
+            .collect())
+    }
+
+    /// Finds the real rooms whose decrypted name contains `pattern` up to `max_edits`
+    /// insertions, deletions or substitutions.
+    /// The rooms are returned with their edit distance, closest matches first.
+    pub fn find_fuzzy(&self, pattern: &str, max_edits: usize) -> Vec<(&Room, &str, usize)> {
+        let mut found: Vec<(&Room, &str, usize)> = self
+            .real_rooms()
+            .map(|(room, name)| (room, name, substring_distance(pattern, name)))
+            .filter(|&(_, _, edits)| edits <= max_edits)
+            .collect();
+
+        found.sort_by_key(|&(room, _, edits)| (edits, room.sector_id));
+
+        found
+    }
+
+    /// Lists the rooms rejected as decoys along with the checksum expected from their name.
+    pub fn mismatches(&self) -> Vec<Mismatch<'_>> {
+        self.rooms
+            .iter()
+            .map(|(room, _)| (room, room.expected_checksum()))
+            .filter(|(room, expected)| *expected != room.checksum)
+            .map(|(room, expected)| Mismatch { room, expected })
+            .collect()
+    }
+}
+
+/// Computes the smallest edit distance between `pattern` and any substring of `text`.
+/// This is the Levenshtein distance where skipping a prefix or suffix of `text` is free.
+fn substring_distance(pattern: &str, text: &str) -> usize {
+    let text: Vec<char> = text.chars().collect();
+
+    // row[j] = distance between the current prefix of the pattern and a substring ending at j
+    let mut row: Vec<usize> = vec![0; text.len() + 1];
+
+    for (idx, ch) in pattern.chars().enumerate() {
+        let mut next: Vec<usize> = vec![idx + 1; text.len() + 1];
+
+        for j in 1..=text.len() {
+            let substitution = row[j - 1] + usize::from(text[j - 1] != ch);
+            next[j] = substitution.min(row[j] + 1).min(next[j - 1] + 1);
+        }
+
+        row = next;
+    }
+
+    row.into_iter().min().expect("row is never empty")
+}
+
 fn main() -> Result<()> {
     let input: Vec<String> = std::fs::read_to_string("input/day-04.txt")?
         .lines()
@@ -109,9 +210,11 @@ fn main() -> Result<()> {
 
     dbg!(part_1);
 
-    let target: Room = rooms
+    let index = RoomIndex::from_input(&input)?;
+
+    let (target, _) = index
+        .find_regex("north ?pole")?
         .into_iter()
-        .filter(|room| room.decrypt().contains("northpole"))
         .next()
         .ok_or(anyhow!("no target found"))?;
 
@@ -153,4 +256,26 @@ mod tests {
 
         assert_eq!("very encrypted name", room.decrypt());
     }
+
+    #[test]
+    fn index() {
+        let input: Vec<String> = INPUT.into_iter().map(String::from).collect();
+        let index = RoomIndex::from_input(&input).unwrap();
+
+        let found = index.find_regex("^z( [a-g])+$").unwrap();
+        assert_eq!(1, found.len());
+        assert_eq!(987, found[0].0.sector_id);
+
+        let found = index.find_fuzzy("tttx", 1);
+        let found: Vec<_> = found
+            .into_iter()
+            .map(|(r, _, e)| (r.sector_id, e))
+            .collect();
+        assert_eq!(vec![(123, 1)], found);
+
+        let mismatches = index.mismatches();
+        assert_eq!(1, mismatches.len());
+        assert_eq!(200, mismatches[0].room.sector_id);
+        assert_eq!("loart", mismatches[0].expected);
+    }
 }
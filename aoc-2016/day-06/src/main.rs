@@ -1,67 +1,147 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{bail, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+const LETTERS: usize = 26;
+
+/// How to choose a character among those counted in a column.
+#[derive(Debug, Clone, Copy)]
+pub enum Strategy {
+    Most,
+    Least,
+    KthMost(usize), // starting from 0 for the most common
+}
+
+/// How to order characters that appear the same number of times.
+#[derive(Debug, Clone, Copy)]
+pub enum TieBreak {
+    Alphabetical,
+    FirstSeen, // the character appearing first in the column comes first
+    Error,     // fail if the chosen character is tied with another one
+}
 
-/// Compute the number of times that each character appears in each column.
-/// Return a vector with one entry per column, containing the character count as hashmap.
-fn column_char_count(input: &Vec<String>) -> Vec<HashMap<char, u32>> {
-    let input: Vec<Vec<char>> = input.into_iter().map(|s| s.chars().collect()).collect();
+/// Character count for a single column.
+#[derive(Debug, Clone, Default)]
+pub struct ColumnCount {
+    counts: [u32; LETTERS],
+    first_seen: [Option<usize>; LETTERS], // row where each character first appears
+}
+
+impl ColumnCount {
+    /// Choose a character according to the strategy.
+    pub fn select(&self, strategy: Strategy, tie_break: TieBreak) -> Result<char> {
+        // rank the characters appearing in the column, most common first
+        // (least common first for `Least`, so that ties are broken the same way)
+        let mut ranked: Vec<usize> = (0..LETTERS).filter(|&ch| self.counts[ch] > 0).collect();
+
+        let count = |ch: usize| match strategy {
+            Strategy::Least => self.counts[ch] as i64,
+            _ => -(self.counts[ch] as i64),
+        };
+
+        match tie_break {
+            TieBreak::FirstSeen => ranked.sort_by_key(|&ch| (count(ch), self.first_seen[ch])),
+            _ => ranked.sort_by_key(|&ch| count(ch)), // stable sort: alphabetical
+        }
 
-    let rows = input.len();
-    let cols = input[0].len();
+        let rank = match strategy {
+            Strategy::Most | Strategy::Least => 0,
+            Strategy::KthMost(k) => k,
+        };
 
-    let mut maps: Vec<HashMap<char, u32>> = Vec::with_capacity(cols);
+        let Some(&ch) = ranked.get(rank) else {
+            bail!("no character of rank {rank} in column");
+        };
 
-    for col in 0..cols {
-        let mut map: HashMap<char, u32> = HashMap::new();
+        if let TieBreak::Error = tie_break {
+            let tied = ranked
+                .iter()
+                .filter(|&&other| self.counts[other] == self.counts[ch])
+                .count();
 
-        for row in 0..rows {
-            let count: &mut u32 = map.entry(input[row][col]).or_insert(0);
-            *count += 1;
+            if tied > 1 {
+                bail!("{tied} characters tied with count {}", self.counts[ch]);
+            }
         }
 
-        maps.push(map);
+        Ok((b'a' + ch as u8) as char)
     }
-
-    maps
 }
 
-/// Compute the message formed by taking the most common character in each column.
-fn column_most_common(maps: &Vec<HashMap<char, u32>>) -> String {
-    maps.into_iter()
-        .map(|map| {
-            map.into_iter()
-                .max_by(|&x, &y| x.1.cmp(&y.1))
-                .expect("max not found: {x}, {y}")
-                .0
-        })
-        .collect()
+/// Column-wise character count of a stream of messages.
+#[derive(Debug, Default)]
+pub struct FrequencyAnalyzer {
+    columns: Vec<ColumnCount>,
+    rows: usize,
 }
 
-/// Compute the message formed by taking the least common character in each column.
-fn column_least_common(maps: &Vec<HashMap<char, u32>>) -> String {
-    maps.into_iter()
-        .map(|map| {
-            map.into_iter()
-                .min_by(|&x, &y| x.1.cmp(&y.1))
-                .expect("min not found: {x}, {y}")
-                .0
-        })
-        .collect()
+impl FrequencyAnalyzer {
+    /// Count the characters of all the lines read from `reader`.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut analyzer = Self::default();
+
+        for line in reader.lines() {
+            analyzer.push(&line?)?;
+        }
+
+        Ok(analyzer)
+    }
+
+    /// Count the characters of a single message.
+    pub fn push(&mut self, line: &str) -> Result<()> {
+        self.push_weighted(line, 1)
+    }
+
+    /// Count the characters of a single message as `weight` votes.
+    /// Blank lines are skipped, so that they do not set the message length.
+    pub fn push_weighted(&mut self, line: &str, weight: u32) -> Result<()> {
+        if line.trim().is_empty() {
+            return Ok(());
+        }
+
+        if self.rows == 0 {
+            self.columns = vec![ColumnCount::default(); line.len()];
+        }
+
+        if line.len() != self.columns.len() {
+            bail!("expected {} characters: {line}", self.columns.len());
+        }
+
+        for (column, byte) in self.columns.iter_mut().zip(line.bytes()) {
+            if !byte.is_ascii_lowercase() {
+                bail!("unexpected character {}: {line}", byte as char);
+            }
+
+            let ch = (byte - b'a') as usize;
+
+            column.counts[ch] += weight;
+            column.first_seen[ch].get_or_insert(self.rows);
+        }
+
+        self.rows += 1;
+
+        Ok(())
+    }
+
+    /// Compute the message formed by choosing a character in each column.
+    pub fn decode(&self, strategy: Strategy, tie_break: TieBreak) -> Result<String> {
+        self.columns
+            .iter()
+            .map(|column| column.select(strategy, tie_break))
+            .collect()
+    }
 }
 
 fn main() -> Result<()> {
-    let input: Vec<String> = std::fs::read_to_string("input/day-06.txt")?
-        .lines()
-        .map(String::from)
-        .collect();
+    let input = BufReader::new(File::open("input/day-06.txt")?);
 
-    let maps = column_char_count(&input);
+    let analyzer = FrequencyAnalyzer::from_reader(input)?;
 
-    let part_1 = column_most_common(&maps);
+    let part_1 = analyzer.decode(Strategy::Most, TieBreak::Error)?;
 
     dbg!(part_1);
 
-    let part_2 = column_least_common(&maps);
+    let part_2 = analyzer.decode(Strategy::Least, TieBreak::Error)?;
 
     dbg!(part_2);
 
@@ -77,19 +157,50 @@ mod tests {
 
     #[test]
     fn part_1() {
-        let input: Vec<String> = INPUT.lines().map(String::from).collect();
-        let maps = column_char_count(&input);
-        let msg = column_most_common(&maps);
+        let analyzer = FrequencyAnalyzer::from_reader(INPUT.as_bytes()).unwrap();
+        let msg = analyzer.decode(Strategy::Most, TieBreak::Error).unwrap();
 
         assert_eq!("easter", msg);
     }
 
     #[test]
     fn part_2() {
-        let input: Vec<String> = INPUT.lines().map(String::from).collect();
-        let maps = column_char_count(&input);
-        let msg = column_least_common(&maps);
+        let analyzer = FrequencyAnalyzer::from_reader(INPUT.as_bytes()).unwrap();
+        let msg = analyzer.decode(Strategy::Least, TieBreak::Error).unwrap();
 
         assert_eq!("advent", msg);
     }
+
+    #[test]
+    fn tie_break() {
+        let analyzer = FrequencyAnalyzer::from_reader("ba\nab\nbc".as_bytes()).unwrap();
+
+        let msg = analyzer.decode(Strategy::Least, TieBreak::Alphabetical);
+        assert_eq!("aa", msg.unwrap());
+
+        let msg = analyzer.decode(Strategy::KthMost(1), TieBreak::FirstSeen);
+        assert_eq!("ab", msg.unwrap());
+
+        assert!(analyzer.decode(Strategy::Most, TieBreak::Error).is_err());
+    }
+
+    #[test]
+    fn weighted() {
+        let mut analyzer = FrequencyAnalyzer::default();
+
+        analyzer.push_weighted("ab", 3).unwrap();
+        analyzer.push("ba").unwrap();
+        analyzer.push("ba").unwrap();
+
+        let msg = analyzer.decode(Strategy::Most, TieBreak::Error);
+        assert_eq!("ab", msg.unwrap());
+    }
+
+    #[test]
+    fn blank_lines() {
+        let analyzer = FrequencyAnalyzer::from_reader("\nab\n\nac\nab\n".as_bytes()).unwrap();
+        let msg = analyzer.decode(Strategy::Most, TieBreak::Error).unwrap();
+
+        assert_eq!("ab", msg);
+    }
 }
@@ -1,119 +1,137 @@
-use anyhow::Result;
-use std::collections::HashSet;
+use anyhow::{bail, Result};
 
-#[derive(Debug)]
-struct IPAddress {
-    supernet: Vec<String>, // outside brackets
-    hypernet: Vec<String>, // inside brackets
-}
-
-impl From<&str> for IPAddress {
-    fn from(string: &str) -> Self {
-        let split: Vec<String> = string.split(&['[', ']']).map(String::from).collect();
+// The address is scanned once, byte by byte, keeping track of whether the current byte
+// is inside brackets (hypernet) or outside (supernet). The patterns are read from the
+// windows ending at the current byte, and are reported as slices of the input line.
 
-        // supernet = outside of brackets = even indices
-        let supernet: Vec<String> = split.clone().into_iter().step_by(2).collect();
-        let hypernet: Vec<String> = split.into_iter().skip(1).step_by(2).collect();
+const LETTERS: usize = 26;
 
-        Self { supernet, hypernet }
-    }
+/// Outcome of the TLS check, with the ABBA justifying it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tls<'a> {
+    Supported(&'a str), // first supernet ABBA
+    Rejected(&'a str),  // first hypernet ABBA
+    NoAbba,
 }
 
-impl From<String> for IPAddress {
-    fn from(string: String) -> Self {
-        Self::from(&string[..])
-    }
+/// Supernet ABA and hypernet BAB justifying the SSL support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Ssl<'a> {
+    aba: &'a str,
+    bab: &'a str,
 }
 
-impl IPAddress {
-    /// Check whether the address supports TLS.
-    /// The address supports TLS if it has a supernet ABBA and no hypernet ABBA.
-    fn supports_tls(&self) -> bool {
-        let hypernet_abba_count = self.hypernet.iter().filter(|s| has_abba(s)).count();
+#[derive(Debug)]
+struct IPAddress<'a> {
+    tls: Tls<'a>,
+    ssl: Option<Ssl<'a>>,
+}
 
-        if hypernet_abba_count > 0 {
-            return false;
+impl<'a> IPAddress<'a> {
+    /// Scan the address in a single pass to check for TLS and SSL support.
+    /// Return an error if the brackets are unbalanced or nested.
+    fn parse(line: &'a str) -> Result<Self> {
+        let bytes = line.as_bytes();
+
+        let mut in_hypernet = false;
+        let mut start = 0; // start of the current sequence
+
+        let mut supernet_abba: Option<&str> = None;
+        let mut hypernet_abba: Option<&str> = None;
+
+        // position of the first ABA (resp. BAB) in supernet (resp. hypernet),
+        // indexed by the pair (A, B) of letters
+        let mut supernet_aba: [Option<usize>; LETTERS * LETTERS] = [None; LETTERS * LETTERS];
+        let mut hypernet_bab: [Option<usize>; LETTERS * LETTERS] = [None; LETTERS * LETTERS];
+        let mut ssl: Option<Ssl> = None;
+
+        for (idx, &byte) in bytes.iter().enumerate() {
+            match byte {
+                b'[' if !in_hypernet => {
+                    in_hypernet = true;
+                    start = idx + 1;
+                    continue;
+                }
+                b']' if in_hypernet => {
+                    in_hypernet = false;
+                    start = idx + 1;
+                    continue;
+                }
+                b'[' | b']' => bail!("unbalanced bracket at position {idx}: {line}"),
+                b'a'..=b'z' => (),
+                _ => bail!("unexpected character at position {idx}: {line}"),
+            }
+
+            // check the 3-characters window ending at the current byte for ABA or BAB
+            if idx >= start + 2 && bytes[idx - 2] == byte && bytes[idx - 1] != byte {
+                let outer = (byte - b'a') as usize;
+                let inner = (bytes[idx - 1] - b'a') as usize;
+
+                let (key, found, other) = match in_hypernet {
+                    false => (outer * LETTERS + inner, &mut supernet_aba, &hypernet_bab),
+                    true => (inner * LETTERS + outer, &mut hypernet_bab, &supernet_aba),
+                };
+
+                found[key].get_or_insert(idx - 2);
+
+                if let (None, Some(pos)) = (ssl, other[key]) {
+                    let (aba, bab) = match in_hypernet {
+                        false => (idx - 2, pos),
+                        true => (pos, idx - 2),
+                    };
+
+                    ssl = Some(Ssl {
+                        aba: &line[aba..(aba + 3)],
+                        bab: &line[bab..(bab + 3)],
+                    });
+                }
+            }
+
+            // check the 4-characters window ending at the current byte for ABBA
+            if idx >= start + 3 {
+                let arr = &bytes[(idx - 3)..=idx];
+
+                if (arr[0] == arr[3]) & (arr[1] == arr[2]) & (arr[0] != arr[1]) {
+                    let abba = match in_hypernet {
+                        false => &mut supernet_abba,
+                        true => &mut hypernet_abba,
+                    };
+
+                    abba.get_or_insert(&line[(idx - 3)..=idx]);
+                }
+            }
         }
 
-        let supernet_abba_count = self.supernet.iter().filter(|s| has_abba(s)).count();
-
-        supernet_abba_count > 0
-    }
-
-    fn supports_ssl(&self) -> bool {
-        // find all ABA patterns in supernet and hypernet
-        let supernet_aba: HashSet<&str> = find_aba_vec(&self.supernet);
-        let hypernet_aba: HashSet<&str> = find_aba_vec(&self.hypernet);
-
-        // reverse the ABA patterns to find the BAB patterns in hypernet
-        let hypernet_bab: HashSet<String> = hypernet_aba
-            .into_iter()
-            .map(|aba| aba.chars().rev().collect())
-            .collect();
-
-        // convert the HashSet<&str> to HashSet<String> to match the type of hypernet_bab
-        let supernet_aba: HashSet<String> = supernet_aba.into_iter().map(String::from).collect();
+        if in_hypernet {
+            bail!("unclosed bracket: {line}");
+        }
 
-        // count the number of supernet ABA with a matching hypernet BAB
-        let intersection = supernet_aba.intersection(&hypernet_bab).count();
+        // the address supports TLS if it has a supernet ABBA and no hypernet ABBA
+        let tls = match (hypernet_abba, supernet_abba) {
+            (Some(abba), _) => Tls::Rejected(abba),
+            (None, Some(abba)) => Tls::Supported(abba),
+            (None, None) => Tls::NoAbba,
+        };
 
-        intersection > 0
+        Ok(Self { tls, ssl })
     }
-}
 
-/// Check whether a string contains the ABBA pattern.
-fn has_abba(string: &str) -> bool {
-    // slide a window of 4 characters across the string
-    for idx in 0..=(string.len() - 4) {
-        let arr: &[u8] = string[idx..(idx + 4)].as_bytes();
-
-        if (arr[0] == arr[3]) & (arr[1] == arr[2]) & (arr[0] != arr[1]) {
-            return true;
-        }
+    /// Check whether the address supports TLS.
+    fn supports_tls(&self) -> bool {
+        matches!(self.tls, Tls::Supported(_))
     }
 
-    false
-}
-
-// The lifetime of the string slices in the returned set is the lifetime of the input.
-// Since we call this function on string slices owned by the `IPAddress` struct only,
-// the resulting `HashSet` remains valid as long as the struct is valid.
-
-/// Find all instances of the pattern ABA in a string.
-/// Return a HashSet containing the first two letters (AB) of each match.
-fn find_aba(string: &str) -> HashSet<&str> {
-    let mut aba: HashSet<&str> = HashSet::new();
-
-    // slide a 3-characters window across the string
-    for idx in 0..=(string.len() - 3) {
-        let slice: &str = &string[idx..(idx + 3)];
-        let arr: &[u8] = slice.as_bytes();
-
-        if (arr[0] == arr[2]) & (arr[0] != arr[1]) {
-            aba.insert(&slice[..2]);
-        }
+    /// Check whether the address supports SSL.
+    /// The address supports SSL if a supernet ABA has a matching hypernet BAB.
+    fn supports_ssl(&self) -> bool {
+        self.ssl.is_some()
     }
-
-    aba
-}
-
-/// Find all instances of ABA in a set of strings.
-fn find_aba_vec(vec: &Vec<String>) -> HashSet<&str> {
-    vec.iter()
-        .map(|s| find_aba(s))
-        .fold(HashSet::new(), |mut set, s| {
-            set = set.union(&s).map(|aba| *aba).collect();
-            set
-        })
 }
 
 fn main() -> Result<()> {
-    let input: Vec<String> = std::fs::read_to_string("input/day-07.txt")?
-        .lines()
-        .map(String::from)
-        .collect();
+    let input: String = std::fs::read_to_string("input/day-07.txt")?;
 
-    let addrs: Vec<IPAddress> = input.into_iter().map(IPAddress::from).collect();
+    let addrs: Vec<IPAddress> = input.lines().map(IPAddress::parse).collect::<Result<_>>()?;
 
     let part_1 = addrs.iter().filter(|&addr| addr.supports_tls()).count();
 
@@ -132,10 +150,10 @@ mod tests {
 
     #[test]
     fn tls() {
-        let addr_1 = IPAddress::from("abba[mnop]qrst");
-        let addr_2 = IPAddress::from("abcd[bddb]xyyx");
-        let addr_3 = IPAddress::from("aaaa[qwer]tyui");
-        let addr_4 = IPAddress::from("ioxxoj[asdfgh]zxcvbn");
+        let addr_1 = IPAddress::parse("abba[mnop]qrst").unwrap();
+        let addr_2 = IPAddress::parse("abcd[bddb]xyyx").unwrap();
+        let addr_3 = IPAddress::parse("aaaa[qwer]tyui").unwrap();
+        let addr_4 = IPAddress::parse("ioxxoj[asdfgh]zxcvbn").unwrap();
 
         assert!(addr_1.supports_tls());
         assert!(!addr_2.supports_tls());
@@ -145,14 +163,33 @@ mod tests {
 
     #[test]
     fn ssl() {
-        let addr_1 = IPAddress::from("aba[bab]xyz");
-        let addr_2 = IPAddress::from("xyx[xyx]xyx");
-        let addr_3 = IPAddress::from("aaa[kek]eke");
-        let addr_4 = IPAddress::from("zazbz[bzb]cdb");
+        let addr_1 = IPAddress::parse("aba[bab]xyz").unwrap();
+        let addr_2 = IPAddress::parse("xyx[xyx]xyx").unwrap();
+        let addr_3 = IPAddress::parse("aaa[kek]eke").unwrap();
+        let addr_4 = IPAddress::parse("zazbz[bzb]cdb").unwrap();
 
         assert!(addr_1.supports_ssl());
         assert!(!addr_2.supports_ssl());
         assert!(addr_3.supports_ssl());
         assert!(addr_4.supports_ssl());
     }
+
+    #[test]
+    fn justification() {
+        let addr = IPAddress::parse("abcd[bddb]xyyx").unwrap();
+        assert_eq!(Tls::Rejected("bddb"), addr.tls);
+
+        let addr = IPAddress::parse("zazbz[bzb]cdb").unwrap();
+        assert_eq!(
+            Some(Ssl {
+                aba: "zbz",
+                bab: "bzb"
+            }),
+            addr.ssl
+        );
+
+        assert!(IPAddress::parse("abba[mnop").is_err());
+        assert!(IPAddress::parse("abba]mnop[qrst]").is_err());
+        assert!(IPAddress::parse("ab[ba[mn]op]").is_err());
+    }
 }
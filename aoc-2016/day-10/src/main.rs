@@ -1,122 +1,399 @@
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt::Write;
 
 // indices for accessing low and high values
 const LO: usize = 0;
 const HI: usize = 1;
 
-enum Recipient {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipient {
     Bot(u32),
     Output(u32),
 }
 
-impl From<(&str, &str)> for Recipient {
-    fn from(data: (&str, &str)) -> Self {
-        let id = data.1.parse::<u32>().unwrap();
+impl TryFrom<(&str, &str)> for Recipient {
+    type Error = anyhow::Error;
+
+    fn try_from(data: (&str, &str)) -> Result<Self> {
+        let id = data.1.parse::<u32>()?;
 
         match data.0 {
-            "bot" => Self::Bot(id),
-            "output" => Self::Output(id),
-            _ => panic!("invalid data: {}", data.0),
+            "bot" => Ok(Self::Bot(id)),
+            "output" => Ok(Self::Output(id)),
+            _ => bail!("invalid recipient: {}", data.0),
         }
     }
 }
 
-fn parse_send_commands(cmds: &Vec<String>) -> HashMap<u32, [Recipient; 2]> {
-    let mut send: HashMap<u32, [Recipient; 2]> = HashMap::with_capacity(cmds.len());
-
-    for cmd in cmds {
-        let split: Vec<&str> = cmd.split_whitespace().collect();
+/// Comparison of two chips by a bot, at a given tick of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub bot: u32,
+    pub low: u32,
+    pub high: u32,
+    pub tick: usize,
+}
 
-        let id = split[1].parse::<u32>().unwrap();
-        let lo = Recipient::from((split[5], split[6]));
-        let hi = Recipient::from((split[10], split[11]));
+/// Chip received by a bot which already held two chips, at a given tick of the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    pub bot: u32,
+    pub chip: u32,
+    pub tick: usize,
+}
 
-        send.insert(id, [lo, hi]); // insert in the [LO, HI] order
+impl std::fmt::Display for Overflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "bot {} received chip {} while holding two chips (tick {})",
+            self.bot, self.chip, self.tick
+        )
     }
+}
 
-    send
+/// Simulation of the bots passing chips around.
+/// At each tick, the first bot to have received two chips compares and hands them over.
+///
+/// A bot waiting for its turn may receive more chips. It then compares its chips two by two,
+/// in the order it received them, as if it had handed over each pair as soon as it got it.
+/// Each extra chip is recorded as an [`Overflow`].
+pub struct Factory {
+    wiring: HashMap<u32, [Recipient; 2]>, // [LO, HI] recipients of each bot
+    bots: HashMap<u32, VecDeque<u32>>,    // chips held by each bot, in the order received
+    outputs: HashMap<u32, Vec<u32>>,
+    ready: VecDeque<u32>, // bots holding two chips, in the order they got them
+    log: Vec<Comparison>,
+    overflows: Vec<Overflow>,
+    tick: usize,
 }
 
-fn parse_init_commands(cmds: &Vec<String>) -> HashMap<u32, Vec<u32>> {
-    let mut bots: HashMap<u32, Vec<u32>> = HashMap::with_capacity(cmds.len());
+impl Factory {
+    pub fn parse(input: &[String]) -> Result<Self> {
+        let mut factory = Self {
+            wiring: HashMap::new(),
+            bots: HashMap::new(),
+            outputs: HashMap::new(),
+            ready: VecDeque::new(),
+            log: Vec::new(),
+            overflows: Vec::new(),
+            tick: 0,
+        };
+
+        let mut values: Vec<(u32, u32)> = Vec::new();
+
+        for line in input {
+            let split: Vec<&str> = line.split_whitespace().collect();
+
+            match split[..] {
+                ["value", value, "goes", "to", "bot", bot] => {
+                    values.push((value.parse()?, bot.parse()?));
+                }
+                ["bot", bot, "gives", "low", "to", lo_kind, lo_id, "and", "high", "to", hi_kind, hi_id] =>
+                {
+                    let lo = Recipient::try_from((lo_kind, lo_id))?;
+                    let hi = Recipient::try_from((hi_kind, hi_id))?;
+
+                    // insert in the [LO, HI] order
+                    if factory.wiring.insert(bot.parse()?, [lo, hi]).is_some() {
+                        bail!("bot {bot} is wired twice");
+                    }
+                }
+                _ => bail!("invalid command: {line}"),
+            }
+        }
 
-    for string in cmds {
-        let split: Vec<&str> = string.split_whitespace().collect();
-        let value = split[1].parse::<u32>().unwrap();
-        let bot_id = split[5].parse::<u32>().unwrap();
+        // hand the initial chips once the wiring is known
+        for (value, bot) in values {
+            factory.give(Recipient::Bot(bot), value);
+        }
 
-        let entry = bots.entry(bot_id).or_insert(Vec::with_capacity(2));
-        entry.push(value);
+        Ok(factory)
     }
 
-    bots
-}
+    /// Hand a chip to a recipient.
+    fn give(&mut self, recipient: Recipient, chip: u32) {
+        match recipient {
+            Recipient::Bot(id) => {
+                let bot = self.bots.entry(id).or_default();
 
-fn main() -> Result<()> {
-    let input: Vec<String> = std::fs::read_to_string("input/day-10.txt")?
-        .lines()
-        .map(String::from)
-        .collect();
+                bot.push_back(chip);
 
-    // split the 'init' and the 'send' commands
-    let (init, send): (Vec<_>, Vec<_>) = input.into_iter().partition(|cmd| &cmd[..3] != "bot");
+                // a bot holding more chips is already waiting in the queue
+                match bot.len() {
+                    2 => self.ready.push_back(id),
+                    len if len > 2 => self.overflows.push(Overflow {
+                        bot: id,
+                        chip,
+                        tick: self.tick,
+                    }),
+                    _ => {}
+                }
+            }
+            Recipient::Output(id) => self.outputs.entry(id).or_default().push(chip),
+        }
+    }
 
-    let send_cmds: HashMap<u32, [Recipient; 2]> = parse_send_commands(&send);
-    let mut bots: HashMap<u32, Vec<u32>> = parse_init_commands(&init);
-    let mut outputs: HashMap<u32, Vec<u32>> = HashMap::new();
-    let mut comparisons: HashMap<u32, [u32; 2]> = HashMap::new();
+    /// Let the next bot holding two chips compare them and pass them on.
+    /// Return `None` when no bot can proceed.
+    pub fn step(&mut self) -> Result<Option<Comparison>> {
+        let Some(bot) = self.ready.pop_front() else {
+            return Ok(None);
+        };
 
-    // build a stack containing the ids of the bots holding two chips
-    let mut stack: Vec<u32> = bots
-        .iter()
-        .filter(|(_, chips)| chips.len() == 2)
-        .map(|(id, _)| *id)
-        .collect();
+        let recipients = *self
+            .wiring
+            .get(&bot)
+            .ok_or(anyhow!("bot {bot} has no instructions"))?;
 
-    // pass the chips from bots on the stack and add recipients to the stack if needed
-    while let Some(sender_id) = stack.pop() {
-        // extract the chips from the sender bot
-        let mut chips: Vec<u32> = bots.get_mut(&sender_id).unwrap().drain(..).collect();
+        // extract the first two chips from the sender bot
+        let held = self.bots.get_mut(&bot).unwrap();
+        let mut chips: Vec<u32> = held.drain(..2).collect();
         chips.sort(); // sort in [lo, hi] order
 
-        // send chips to recipients and add bot recipients holding two chips to the stack
+        // the bot takes another turn with the chips it received in the meantime
+        if held.len() >= 2 {
+            self.ready.push_back(bot);
+        }
+
         for pos in [LO, HI] {
-            let chip = chips[pos];
+            self.give(recipients[pos], chips[pos]);
+        }
 
-            match send_cmds[&sender_id][pos] {
-                Recipient::Bot(id) => {
-                    let bot = bots.entry(id).or_insert(Vec::with_capacity(2));
-                    bot.push(chip);
+        let comparison = Comparison {
+            bot,
+            low: chips[LO],
+            high: chips[HI],
+            tick: self.tick,
+        };
 
-                    if bot.len() == 2 {
-                        stack.push(id); // add bot holding two chips to the stack
-                    }
-                }
-                Recipient::Output(id) => {
-                    let output = outputs.entry(id).or_insert(Vec::new());
-                    output.push(chip);
-                }
+        self.log.push(comparison);
+        self.tick += 1;
+
+        Ok(Some(comparison))
+    }
+
+    /// Run the simulation until no bot can proceed.
+    pub fn run(&mut self) -> Result<()> {
+        while self.step()?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Every comparison done so far, in order.
+    pub fn comparisons(&self) -> &[Comparison] {
+        &self.log
+    }
+
+    /// Every chip received by a bot already holding two chips, in order.
+    pub fn overflows(&self) -> &[Overflow] {
+        &self.overflows
+    }
+
+    /// Find the first bot which compared the chips `a` and `b`.
+    pub fn who_compared(&self, a: u32, b: u32) -> Option<u32> {
+        let (low, high) = (a.min(b), a.max(b));
+
+        self.log
+            .iter()
+            .find(|cmp| (cmp.low, cmp.high) == (low, high))
+            .map(|cmp| cmp.bot)
+    }
+
+    /// Bots stuck holding a single chip, with that chip.
+    pub fn stuck(&self) -> Vec<(u32, u32)> {
+        let mut stuck: Vec<(u32, u32)> = self
+            .bots
+            .iter()
+            .filter(|(_, chips)| chips.len() == 1)
+            .map(|(&id, chips)| (id, chips[0]))
+            .collect();
+
+        stuck.sort();
+
+        stuck
+    }
+
+    /// Chips that were put in an output bin.
+    pub fn output(&self, id: u32) -> &[u32] {
+        self.outputs.get(&id).map_or(&[], |chips| chips)
+    }
+
+    /// Export the wiring of bots and outputs as a Graphviz DOT graph.
+    pub fn to_dot(&self) -> String {
+        let mut wiring: Vec<_> = self.wiring.iter().collect();
+        wiring.sort_by_key(|(&id, _)| id);
+
+        let node = |recipient: Recipient| match recipient {
+            Recipient::Bot(id) => format!("bot_{id}"),
+            Recipient::Output(id) => format!("output_{id}"),
+        };
+
+        let mut dot = String::from("digraph factory {\n");
+
+        for (&id, recipients) in wiring {
+            let bot = node(Recipient::Bot(id));
+
+            for (pos, label) in [(LO, "low"), (HI, "high")] {
+                let target = node(recipients[pos]);
+                writeln!(dot, "    {bot} -> {target} [label=\"{label}\"];").unwrap();
             }
+        }
+
+        let outputs: BTreeSet<u32> = self
+            .wiring
+            .values()
+            .flatten()
+            .filter_map(|recipient| match recipient {
+                Recipient::Output(id) => Some(*id),
+                Recipient::Bot(_) => None,
+            })
+            .collect();
 
-            // track the comparison done by the sender bot
-            // NOTE this assumes that every bot does a single comparison, which seems true
-            comparisons.insert(sender_id, [chips[LO], chips[HI]]);
+        for id in outputs {
+            writeln!(dot, "    output_{id} [shape=box];").unwrap();
         }
+
+        dot.push_str("}\n");
+
+        dot
+    }
+}
+
+fn main() -> Result<()> {
+    let input: Vec<String> = std::fs::read_to_string("input/day-10.txt")?
+        .lines()
+        .map(String::from)
+        .collect();
+
+    let mut factory = Factory::parse(&input)?;
+    factory.run()?;
+
+    for overflow in factory.overflows() {
+        println!("warning: {overflow}");
+    }
+
+    let stuck = factory.stuck();
+
+    if !stuck.is_empty() {
+        bail!("bots stuck with a single chip: {stuck:?}");
     }
 
-    let part_1 = comparisons
-        .iter()
-        .filter(|(_, chips)| **chips == [17, 61])
-        .next()
-        .unwrap()
-        .0;
+    let part_1 = factory
+        .who_compared(17, 61)
+        .ok_or(anyhow!("no bot compared 17 and 61"))?;
 
     dbg!(part_1);
 
-    let part_2 = outputs[&0][0] * outputs[&1][0] * outputs[&2][0];
+    let chip = |id: u32| {
+        factory
+            .output(id)
+            .first()
+            .copied()
+            .ok_or(anyhow!("output {id} is empty"))
+    };
+
+    let part_2 = chip(0)? * chip(1)? * chip(2)?;
 
     dbg!(part_2);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Factory {
+        let input: Vec<String> = input.lines().map(String::from).collect();
+        Factory::parse(&input).unwrap()
+    }
+
+    const INPUT: &str = "value 5 goes to bot 2\n\
+                         bot 2 gives low to bot 1 and high to bot 0\n\
+                         value 3 goes to bot 1\n\
+                         bot 1 gives low to output 1 and high to bot 0\n\
+                         bot 0 gives low to output 2 and high to output 0\n\
+                         value 2 goes to bot 2";
+
+    #[test]
+    fn part_1() {
+        let mut factory = parse(INPUT);
+        factory.run().unwrap();
+
+        assert!(factory.overflows().is_empty());
+        assert_eq!(factory.who_compared(5, 2), Some(2));
+        assert_eq!(factory.who_compared(2, 3), Some(1));
+        assert_eq!(factory.comparisons().len(), 3);
+        assert!(factory.stuck().is_empty());
+    }
+
+    #[test]
+    fn part_2() {
+        let mut factory = parse(INPUT);
+        factory.run().unwrap();
+
+        assert_eq!(factory.output(0), &[5]);
+        assert_eq!(factory.output(1), &[2]);
+        assert_eq!(factory.output(2), &[3]);
+        assert_eq!(factory.output(3), &[] as &[u32]);
+    }
+
+    #[test]
+    fn chip_received_while_waiting() {
+        // bot 1 holds two chips and waits behind bot 0, which hands it a third one
+        let mut factory = parse(
+            "value 1 goes to bot 0\n\
+             value 2 goes to bot 0\n\
+             value 3 goes to bot 1\n\
+             value 4 goes to bot 1\n\
+             bot 0 gives low to bot 1 and high to output 0\n\
+             bot 1 gives low to output 1 and high to output 2",
+        );
+
+        factory.run().unwrap();
+
+        assert_eq!(factory.who_compared(1, 2), Some(0));
+        assert_eq!(factory.who_compared(3, 4), Some(1));
+        assert_eq!(factory.stuck(), vec![(1, 1)]);
+        assert_eq!(
+            factory.overflows(),
+            &[Overflow {
+                bot: 1,
+                chip: 1,
+                tick: 0
+            }]
+        );
+        assert_eq!(
+            factory.overflows()[0].to_string(),
+            "bot 1 received chip 1 while holding two chips (tick 0)"
+        );
+    }
+
+    #[test]
+    fn to_dot() {
+        let factory = parse(INPUT);
+
+        let expected = "digraph factory {
+    bot_0 -> output_2 [label=\"low\"];
+    bot_0 -> output_0 [label=\"high\"];
+    bot_1 -> output_1 [label=\"low\"];
+    bot_1 -> bot_0 [label=\"high\"];
+    bot_2 -> bot_1 [label=\"low\"];
+    bot_2 -> bot_0 [label=\"high\"];
+    output_0 [shape=box];
+    output_1 [shape=box];
+    output_2 [shape=box];
+}
+";
+        assert_eq!(factory.to_dot(), expected);
+    }
+
+    #[test]
+    fn missing_wiring() {
+        let mut factory = parse("value 1 goes to bot 0\nvalue 2 goes to bot 0");
+
+        assert!(factory.step().is_err());
+    }
+}
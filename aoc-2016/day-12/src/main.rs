@@ -1,20 +1,24 @@
 use anyhow::Result;
 use std::collections::HashMap;
 
+use arithmetic::{Arithmetic, Checked, Fault, RuntimeError};
+
+#[derive(Debug, Clone, Copy)]
 enum IntOrReg {
-    Int(i32),
+    Int(i64),
     Reg(char),
 }
 
 impl From<&str> for IntOrReg {
     fn from(string: &str) -> Self {
-        match string.parse::<i32>() {
+        match string.parse::<i64>() {
             Ok(int) => Self::Int(int),
             Err(_) => Self::Reg(string.chars().next().unwrap()),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 enum Instruction {
     Cpy { x: IntOrReg, y: char },
     Inc(char),
@@ -44,32 +48,63 @@ impl From<&str> for Instruction {
     }
 }
 
-fn execute_instructions(regs: &mut HashMap<char, i32>, instructions: &Vec<Instruction>) {
+/// Execute a single instruction and return the offset to the next one.
+fn execute_instruction<A: Arithmetic>(
+    regs: &mut HashMap<char, A::Word>,
+    instruction: &Instruction,
+) -> Result<i32, Fault> {
+    match instruction {
+        Instruction::Inc(reg) => {
+            let val = regs.get_mut(reg).unwrap();
+            *val = A::add(val, &A::word(1)?)?;
+        }
+        Instruction::Dec(reg) => {
+            let val = regs.get_mut(reg).unwrap();
+            *val = A::sub(val, &A::word(1)?)?;
+        }
+        Instruction::Cpy { x, y } => match x {
+            IntOrReg::Int(val) => *regs.get_mut(y).unwrap() = A::word(*val)?,
+            IntOrReg::Reg(reg) => *regs.get_mut(y).unwrap() = regs[reg].clone(),
+        },
+        Instruction::Jnz { x, y } => {
+            let val = match x {
+                IntOrReg::Int(val) => A::word(*val)?,
+                IntOrReg::Reg(reg) => regs[reg].clone(),
+            };
+
+            if val != A::zero() {
+                return Ok(*y);
+            }
+        }
+    };
+
+    Ok(1)
+}
+
+/// Execute the instructions with the registers in `regs`, using the arithmetic `A`.
+/// Return an error with the index of the instruction if an operation overflows.
+fn execute_instructions<A: Arithmetic>(
+    regs: &mut HashMap<char, A::Word>,
+    instructions: &[Instruction],
+) -> Result<(), RuntimeError<Instruction>> {
     let mut ind = 0;
 
-    while ind < instructions.len() {
-        match &instructions[ind] {
-            Instruction::Inc(reg) => *regs.get_mut(&reg).unwrap() += 1,
-            Instruction::Dec(reg) => *regs.get_mut(&reg).unwrap() -= 1,
-            Instruction::Cpy { x, y } => match x {
-                IntOrReg::Int(val) => *regs.get_mut(y).unwrap() = *val,
-                IntOrReg::Reg(reg) => *regs.get_mut(y).unwrap() = *regs.get(reg).unwrap(),
-            },
-            Instruction::Jnz { x, y } => {
-                let val = match x {
-                    IntOrReg::Int(val) => *val,
-                    IntOrReg::Reg(reg) => *regs.get(reg).unwrap(),
-                };
-
-                if val != 0 {
-                    // cannot cast y as usize as it can be negative
-                    ind = ((ind as i32) + y - 1) as usize; // -1 to offset the +1 after the loop
-                }
+    while 0 <= ind && ind < instructions.len() as i32 {
+        let instruction = &instructions[ind as usize];
+
+        match execute_instruction::<A>(regs, instruction) {
+            Ok(offset) => ind += offset,
+            Err(fault) => {
+                return Err(RuntimeError {
+                    fault,
+                    pc: ind as usize,
+                    cmd: *instruction,
+                })
             }
-        };
-
-        ind += 1;
+        }
     }
+
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -79,14 +114,14 @@ fn main() -> Result<()> {
         .collect();
 
     let mut regs: HashMap<char, i32> = HashMap::from([('a', 0), ('b', 0), ('c', 0), ('d', 0)]);
-    execute_instructions(&mut regs, &instructions);
+    execute_instructions::<Checked<i32>>(&mut regs, &instructions)?;
 
     let part_1 = regs[&'a'];
 
     dbg!(part_1);
 
     let mut regs: HashMap<char, i32> = HashMap::from([('a', 0), ('b', 0), ('c', 1), ('d', 0)]);
-    execute_instructions(&mut regs, &instructions);
+    execute_instructions::<Checked<i32>>(&mut regs, &instructions)?;
 
     let part_2 = regs[&'a'];
 
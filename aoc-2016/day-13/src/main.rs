@@ -1,13 +1,15 @@
 use anyhow::Result;
-use itertools::iproduct;
 use num_complex::Complex;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 
 type Node = Complex<i32>;
 
 const ORIGIN: Node = Node::new(1, 1); // starting node (not (0, 0))
 const TARGET: Node = Node::new(31, 39); // target node
 
+const MAX_STEPS: u32 = 1000; // give up the search for paths longer than this
+
 const SHIFTS: [Node; 4] = [
     Complex::new(1, 0),  // right
     Complex::new(-1, 0), // left
@@ -16,78 +18,157 @@ const SHIFTS: [Node; 4] = [
 ];
 
 fn is_open_space(x: i32, y: i32, input: i32) -> bool {
-    let mut val = x * x + 3 * x + 2 * x * y + y + y * y + input;
-    let mut count = 0;
+    let val = x * x + 3 * x + 2 * x * y + y + y * y + input;
 
-    while val > 0 {
-        count += val & 1;
-        val = val >> 1;
-    }
+    val.count_ones().is_multiple_of(2)
+}
 
-    count % 2 == 0
+/// Reason why no path was found by a search.
+#[derive(Debug, PartialEq, Eq)]
+enum SearchError {
+    Unreachable(Node),       // no path leads to the node
+    LimitReached(Node, u32), // the node is further than the step limit, if reachable
 }
 
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::Unreachable(target) => write!(f, "no path found to {target}"),
+            SearchError::LimitReached(target, max_steps) => {
+                write!(
+                    f,
+                    "search limit of {max_steps} steps reached before {target}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SearchError {}
+
 // ----------------------------------------------
 
-struct Graph {
-    nodes: HashSet<Node>,
+// The maze covers the whole quadrant x, y >= 0 and has no natural bound, so the tiles are
+// only computed when a search reaches them, and cached for later searches.
+
+struct Maze {
+    input: i32,                 // office designer's favorite number
+    tiles: HashMap<Node, bool>, // cache: node -> is open space
 }
 
-impl Graph {
-    fn new(max_node: Node, input: i32) -> Self {
-        let mut nodes = HashSet::new();
+impl Maze {
+    fn new(input: i32) -> Self {
+        Self {
+            input,
+            tiles: HashMap::new(),
+        }
+    }
 
-        for (x, y) in iproduct!(0..=max_node.re, 0..=max_node.im) {
-            if is_open_space(x, y, input) {
-                nodes.insert(Node::new(x, y));
-            }
+    /// Check whether the node is an open space, computing it on first access.
+    /// Nodes with a negative coordinate are outside of the building.
+    fn is_open(&mut self, node: Node) -> bool {
+        if node.re < 0 || node.im < 0 {
+            return false;
         }
 
-        Self { nodes }
+        *self
+            .tiles
+            .entry(node)
+            .or_insert_with(|| is_open_space(node.re, node.im, self.input))
     }
 
-    fn neighbors(&self, node: &Node) -> HashSet<&Node> {
-        let mut neighbors = HashSet::new();
+    fn neighbors(&mut self, node: Node) -> Vec<Node> {
+        SHIFTS
+            .into_iter()
+            .map(|shift| node + shift)
+            .filter(|&other| self.is_open(other))
+            .collect()
+    }
+
+    /// Compute the distance from `origin` to every node reachable in at most `max_steps` (BFS).
+    /// The search stops early once `target` is reached, if given.
+    fn explore(
+        &mut self,
+        origin: Node,
+        max_steps: u32,
+        target: Option<Node>,
+    ) -> HashMap<Node, u32> {
+        let mut distances: HashMap<Node, u32> = HashMap::new();
+
+        if !self.is_open(origin) {
+            return distances;
+        }
+
+        let mut queue: VecDeque<Node> = VecDeque::from([origin]);
+        distances.insert(origin, 0);
+
+        while let Some(node) = queue.pop_front() {
+            let dist = distances[&node];
+
+            if Some(node) == target || dist == max_steps {
+                break;
+            }
 
-        for shift in SHIFTS {
-            if let Some(other) = self.nodes.get(&(node + shift)) {
-                neighbors.insert(other);
+            for neighbor in self.neighbors(node) {
+                distances.entry(neighbor).or_insert_with(|| {
+                    queue.push_back(neighbor);
+                    dist + 1
+                });
             }
         }
 
-        neighbors
+        distances
     }
-}
 
-// ----------------------------------------------
+    /// Length of the shortest path from `origin` to `target`, if there is one
+    /// of length at most `max_steps`.
+    fn distance(&mut self, origin: Node, target: Node, max_steps: u32) -> Result<u32, SearchError> {
+        if !self.is_open(target) {
+            return Err(SearchError::Unreachable(target));
+        }
+
+        let distances = self.explore(origin, max_steps, Some(target));
 
-fn dijkstra(graph: &Graph) -> HashMap<&Node, u32> {
-    let mut unvisited: HashSet<&Node> = graph.nodes.iter().collect();
-    let mut distances: HashMap<&Node, u32> =
-        graph.nodes.iter().map(|node| (node, u32::MAX)).collect();
-
-    *distances.get_mut(&ORIGIN).unwrap() = 0; // ORIGIN should always be in the graph
-
-    // while the univisited set is not empty, get the node with smallest distance
-    while let Some((&node, &dist)) = distances
-        .iter()
-        .filter(|(&node, _)| unvisited.contains(node))
-        .min_by_key(|(_, &dist)| dist)
-    {
-        if dist == u32::MAX {
-            break;
+        if let Some(&dist) = distances.get(&target) {
+            return Ok(dist);
         }
 
-        for neighbor in graph.neighbors(node) {
-            // the neighbors are in the graph by construction so unwrap is ok
-            let prev_dist: u32 = *distances.get(neighbor).unwrap();
-            *distances.get_mut(neighbor).unwrap() = u32::min(prev_dist, dist + 1);
+        // the search was cut short if some nodes were left at the limit
+        match distances.values().any(|&dist| dist == max_steps) {
+            true => Err(SearchError::LimitReached(target, max_steps)),
+            false => Err(SearchError::Unreachable(target)),
         }
+    }
 
-        let _ = unvisited.remove(node);
+    /// Nodes reachable from `origin` in at most `steps` steps.
+    fn reachable(&mut self, origin: Node, steps: u32) -> HashSet<Node> {
+        self.explore(origin, steps, None).into_keys().collect()
     }
 
-    distances
+    /// Render the region explored so far, walls as '#', open spaces as '.',
+    /// and the nodes not computed yet as ' '.
+    fn render(&self) -> String {
+        let max_x = self.tiles.keys().map(|node| node.re).max().unwrap_or(0);
+        let max_y = self.tiles.keys().map(|node| node.im).max().unwrap_or(0);
+
+        let mut render = String::new();
+
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                let ch = match self.tiles.get(&Node::new(x, y)) {
+                    Some(true) => '.',
+                    Some(false) => '#',
+                    None => ' ',
+                };
+
+                render.push(ch);
+            }
+
+            render.push('\n');
+        }
+
+        render
+    }
 }
 
 fn main() -> Result<()> {
@@ -95,28 +176,21 @@ fn main() -> Result<()> {
         .trim()
         .parse::<i32>()?;
 
-    // Write TARGET = (Tx, Ty).
-    // Define a graph with containing all nodes in a rectangle delimited by max_node = k * TARGET.
-    // The minimal length of a path passing outside of the rectangle is min{ (2k-1)Tx + Ty, (2k-1)Ty + Tx }.
-    // A shortest path inside the rectangle with length less than this it is the global shortest path,
-    // and not just the shortest in the rectangle.
-
-    // initialize a graph containing all nodes in a rectangle centered at TARGET
-    let graph = Graph::new(2 * TARGET, input);
-
-    let distances = dijkstra(&graph);
+    let mut maze = Maze::new(input);
 
-    let part_1 = *distances.get(&TARGET).unwrap();
-
-    // check that the path found is the global shortest path
-    assert!(part_1 < i32::min(3 * TARGET.re + TARGET.im, 3 * TARGET.im + TARGET.re) as u32);
+    let part_1 = maze.distance(ORIGIN, TARGET, MAX_STEPS)?;
 
     dbg!(part_1);
 
-    let part_2 = distances.iter().filter(|(_, &dist)| dist <= 50).count();
+    let part_2 = maze.reachable(ORIGIN, 50).len();
 
     dbg!(part_2);
 
+    // the explored region is drawn if asked for
+    if std::env::args().nth(1).as_deref() == Some("--render") {
+        println!("{}", maze.render());
+    }
+
     Ok(())
 }
 
@@ -125,11 +199,61 @@ mod tests {
     use super::*;
 
     #[test]
-    fn dijk() {
-        let graph = Graph::new(Node::new(9, 6), 10);
+    fn distance() {
+        let mut maze = Maze::new(10);
+
+        assert_eq!(Ok(11), maze.distance(ORIGIN, Node::new(7, 4), MAX_STEPS));
+        assert_eq!(
+            Err(SearchError::LimitReached(Node::new(7, 4), 10)),
+            maze.distance(ORIGIN, Node::new(7, 4), 10)
+        );
+
+        // a wall as target, and a wall as origin
+        assert_eq!(
+            Err(SearchError::Unreachable(Node::new(1, 0))),
+            maze.distance(ORIGIN, Node::new(1, 0), MAX_STEPS)
+        );
+        assert_eq!(
+            Err(SearchError::Unreachable(Node::new(7, 4))),
+            maze.distance(Node::new(1, 0), Node::new(7, 4), MAX_STEPS)
+        );
+    }
+
+    #[test]
+    fn reachable() {
+        let mut maze = Maze::new(10);
+
+        assert_eq!(1, maze.reachable(ORIGIN, 0).len());
+        assert_eq!(3, maze.reachable(ORIGIN, 1).len());
+    }
+
+    #[test]
+    fn render() {
+        let mut maze = Maze::new(10);
+
+        for x in 0..10 {
+            for y in 0..7 {
+                maze.is_open(Node::new(x, y));
+            }
+        }
+
+        let expected = "\
+.#.####.##
+..#..#...#
+#....##...
+###.#.###.
+.##..#..#.
+..##....#.
+#...##.###
+";
+        assert_eq!(expected, maze.render());
+
+        // the nodes not computed are left blank
+        let mut maze = Maze::new(10);
 
-        let dists = dijkstra(&graph);
+        maze.is_open(Node::new(0, 0));
+        maze.is_open(Node::new(2, 1));
 
-        assert_eq!(Some(&11), dists.get(&Node::new(7, 4)));
+        assert_eq!(".  \n  #\n", maze.render());
     }
 }
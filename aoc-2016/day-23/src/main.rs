@@ -1,15 +1,18 @@
 use anyhow::Result;
 
+use arithmetic::{Arithmetic, Checked, Fault, RuntimeError};
+use decompiler::{Cond, Decompiler, Lift, Op};
+
 /// Enum representing an argument to a command.
-#[derive(Clone, Copy)]
+#[derive(Debug, Clone, Copy)]
 enum Arg {
-    Int(i32),   // integer
+    Int(i64),   // integer
     Reg(usize), // register
 }
 
 impl From<&str> for Arg {
     fn from(string: &str) -> Self {
-        if let Ok(int) = string.parse::<i32>() {
+        if let Ok(int) = string.parse::<i64>() {
             Self::Int(int)
         } else {
             let reg = string.chars().next().unwrap();
@@ -18,10 +21,19 @@ impl From<&str> for Arg {
     }
 }
 
+impl std::fmt::Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::Int(int) => write!(f, "{int}"),
+            Arg::Reg(reg) => write!(f, "{}", (b'a' + *reg as u8) as char),
+        }
+    }
+}
+
 // ----------------------------------------------
 
 /// Command enum reprensenting a command (= instruction).
-#[derive(Clone)]
+#[derive(Debug, Clone)]
 enum Cmd {
     Inc(Arg),      // [Reg]: increase arg0
     Dec(Arg),      // [Reg]: decrease arg0
@@ -33,7 +45,7 @@ enum Cmd {
 impl From<&str> for Cmd {
     fn from(string: &str) -> Self {
         let split: Vec<&str> = string.split_whitespace().collect();
-        let args: Vec<Arg> = split[1..].into_iter().map(|&s| Arg::from(s)).collect();
+        let args: Vec<Arg> = split[1..].iter().map(|&s| Arg::from(s)).collect();
 
         match split[0] {
             "inc" => Cmd::Inc(args[0]),
@@ -46,41 +58,85 @@ impl From<&str> for Cmd {
     }
 }
 
+impl Lift for Cmd {
+    fn lift(&self, idx: usize) -> Op {
+        match *self {
+            Cmd::Inc(reg @ Arg::Reg(_)) => Op::Stmt(format!("{reg} += 1")),
+            Cmd::Dec(reg @ Arg::Reg(_)) => Op::Stmt(format!("{reg} -= 1")),
+            Cmd::Cpy(arg0, reg @ Arg::Reg(_)) => Op::Stmt(format!("{reg} = {arg0}")),
+            Cmd::Jnz(Arg::Int(0), _) => Op::Nop,
+            Cmd::Jnz(Arg::Int(_), Arg::Int(jmp)) => Op::Jump(Cond::Always, idx as i64 + jmp),
+            Cmd::Jnz(reg, Arg::Int(jmp)) => {
+                Op::Jump(Cond::NonZero(reg.to_string()), idx as i64 + jmp)
+            }
+            Cmd::Jnz(arg0, arg1) => Op::Opaque(format!("jump {arg1} if {arg0} != 0")),
+            Cmd::Tgl(arg0) => Op::Opaque(format!("toggle {arg0}")),
+            _ => Op::Nop, // invalid commands are skipped
+        }
+    }
+}
+
 // ----------------------------------------------
 
-struct Program {
-    registers: [i32; 4], // values of the registers ['a', 'b', 'c', 'd']
+struct Program<A: Arithmetic> {
+    registers: [A::Word; 4], // values of the registers ['a', 'b', 'c', 'd']
     commands: Vec<Cmd>,
 }
 
-impl Program {
+impl<A: Arithmetic> Program<A> {
+    /// Create a program with the registers initialized to the given values.
+    fn new(registers: [i64; 4], commands: Vec<Cmd>) -> Result<Self, Fault> {
+        let registers = [
+            A::word(registers[0])?,
+            A::word(registers[1])?,
+            A::word(registers[2])?,
+            A::word(registers[3])?,
+        ];
+
+        Ok(Self {
+            registers,
+            commands,
+        })
+    }
+
+    /// Return the value of `arg` (integer or register).
+    fn value(&self, arg: &Arg) -> Result<A::Word, Fault> {
+        match *arg {
+            Arg::Int(int) => A::word(int),
+            Arg::Reg(reg) => Ok(self.registers[reg].clone()),
+        }
+    }
+
     /// Increment the value of `arg0` (register).
-    fn increment(&mut self, arg0: &Arg) {
+    fn increment(&mut self, arg0: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = arg0 {
-            self.registers[*reg] += 1;
+            self.registers[*reg] = A::add(&self.registers[*reg], &A::word(1)?)?;
         }
+
+        Ok(())
     }
 
     /// Decrement the value of `arg0` (register).
-    fn decrement(&mut self, arg0: &Arg) {
+    fn decrement(&mut self, arg0: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = arg0 {
-            self.registers[*reg] -= 1;
+            self.registers[*reg] = A::sub(&self.registers[*reg], &A::word(1)?)?;
         }
+
+        Ok(())
     }
 
     /// Toggle the instruction `arg0` (integer or register) steps away of position `ind`.
-    fn toggle(&mut self, arg0: &Arg, ind: usize) {
-        let x: i32 = match *arg0 {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => self.registers[reg],
-        };
+    fn toggle(&mut self, arg0: &Arg, ind: usize) -> Result<(), Fault> {
+        let x: i64 = A::int(&self.value(arg0)?)?;
 
-        let ind = (ind as i32 + x) as usize; // index of the instruction to toggle
+        let ind = ind as i64 + x; // index of the instruction to toggle
 
-        if ind >= self.commands.len() {
-            return;
+        if ind < 0 || ind >= self.commands.len() as i64 {
+            return Ok(());
         }
 
+        let ind = ind as usize;
+
         let new_command = match self.commands[ind] {
             Cmd::Inc(arg0) => Cmd::Dec(arg0),
             Cmd::Dec(arg0) => Cmd::Inc(arg0),
@@ -90,65 +146,65 @@ impl Program {
         };
 
         self.commands[ind] = new_command;
+
+        Ok(())
     }
 
     /// Copy the value of `arg0` (integer or register) into `arg1` (register).
-    fn copy(&mut self, arg0: &Arg, arg1: &Arg) {
-        let val = match *arg0 {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => self.registers[reg],
-        };
+    fn copy(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
+        let val = self.value(arg0)?;
 
-        match *arg1 {
-            Arg::Reg(reg) => self.registers[reg] = val,
-            _ => return,
-        };
+        if let Arg::Reg(reg) = *arg1 {
+            self.registers[reg] = val;
+        }
+
+        Ok(())
     }
 
     /// Jump `arg1` (integer or register) steps if `arg0` (integer or register) is not zero.
     /// Return the number of commands to jump minus one (to offset the skip).
-    fn jump(&self, arg0: &Arg, arg1: &Arg) -> i32 {
-        let jmp = match *arg1 {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => self.registers[reg],
-        };
-
-        let val = match *arg0 {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => self.registers[reg],
-        };
+    fn jump(&self, arg0: &Arg, arg1: &Arg) -> Result<i64, Fault> {
+        let jmp = A::int(&self.value(arg1)?)?;
+        let val = self.value(arg0)?;
 
-        match val {
-            0 => 0, // return 0 to ignore the command
-            _ => jmp - 1,
+        match val == A::zero() {
+            true => Ok(0), // return 0 to ignore the command
+            false => Ok(jmp - 1),
         }
     }
 
     /// Execute the command at index `ind` and return the new index.
-    fn execute(&mut self, ind: usize) -> usize {
-        let mut ind = ind;
-
-        match self.commands[ind] {
-            Cmd::Inc(arg0) => self.increment(&arg0),
-            Cmd::Dec(arg0) => self.decrement(&arg0),
-            Cmd::Tgl(arg0) => self.toggle(&arg0, ind),
-            Cmd::Cpy(arg0, arg1) => self.copy(&arg0, &arg1),
-            Cmd::Jnz(arg0, arg1) => {
-                let jmp = self.jump(&arg0, &arg1);
-                ind = (ind as i32 + jmp) as usize; // new index - 1
-            }
-        }
+    /// Return an error if the command overflows, with its index.
+    fn execute(&mut self, ind: usize) -> Result<i64, RuntimeError<Cmd>> {
+        let cmd = self.commands[ind].clone();
+
+        let jmp = match cmd {
+            Cmd::Inc(arg0) => self.increment(&arg0).map(|_| 0),
+            Cmd::Dec(arg0) => self.decrement(&arg0).map(|_| 0),
+            Cmd::Tgl(arg0) => self.toggle(&arg0, ind).map(|_| 0),
+            Cmd::Cpy(arg0, arg1) => self.copy(&arg0, &arg1).map(|_| 0),
+            Cmd::Jnz(arg0, arg1) => self.jump(&arg0, &arg1),
+        };
 
-        ind + 1
+        match jmp {
+            Ok(jmp) => Ok(ind as i64 + jmp + 1),
+            Err(fault) => Err(RuntimeError {
+                fault,
+                pc: ind,
+                cmd,
+            }),
+        }
     }
 
     /// Run the entire program.
-    fn run(&mut self) {
+    fn run(&mut self) -> Result<(), RuntimeError<Cmd>> {
         let mut ind = 0;
 
-        while ind < self.commands.len() {
-            ind = self.execute(ind);
+        while 0 <= ind && ind < self.commands.len() as i64 {
+            ind = self.execute(ind as usize)?;
         }
+
+        Ok(())
     }
 }
 
@@ -162,12 +218,17 @@ fn main() -> Result<()> {
 
     // ----------------------------------------------
 
-    let mut program = Program {
-        registers: [7, 0, 0, 0],
-        commands: commands.clone(),
-    };
+    // the toggles modify the program, so this only describes its initial state
+    let decompiler = Decompiler::new(&commands);
+
+    println!("{}", decompiler.pseudocode());
+    println!("{}", decompiler.loop_table());
+
+    // ----------------------------------------------
+
+    let mut program: Program<Checked<i32>> = Program::new([7, 0, 0, 0], commands.clone())?;
 
-    program.run();
+    program.run()?;
 
     let part_1 = program.registers[0];
 
@@ -175,12 +236,9 @@ fn main() -> Result<()> {
 
     // ----------------------------------------------
 
-    let mut program = Program {
-        registers: [12, 0, 0, 0],
-        commands,
-    };
+    let mut program: Program<Checked<i32>> = Program::new([12, 0, 0, 0], commands)?;
 
-    program.run();
+    program.run()?;
 
     let part_2 = program.registers[0];
 
@@ -192,6 +250,8 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use arithmetic::{Saturating, Unbounded, Wrapping};
+    use num_bigint::BigInt;
 
     const INP12: [&str; 6] = ["cpy 41 a", "inc a", "inc a", "dec a", "jnz a 2", "dec a"];
     const INP23: [&str; 7] = [
@@ -200,25 +260,89 @@ mod tests {
 
     #[test]
     fn test_input_day_12() {
-        let mut prog = Program {
+        let mut prog: Program<Checked<i32>> = Program {
             registers: [0, 0, 0, 0],
             commands: INP12.into_iter().map(Cmd::from).collect(),
         };
 
-        prog.run();
+        prog.run().unwrap();
 
         assert_eq!(prog.registers[0], 42);
     }
 
     #[test]
     fn test_input_day_23() {
-        let mut prog = Program {
+        let mut prog: Program<Checked<i32>> = Program {
             registers: [0, 0, 0, 0],
             commands: INP23.into_iter().map(Cmd::from).collect(),
         };
 
-        prog.run();
+        prog.run().unwrap();
 
         assert_eq!(prog.registers[0], 3);
     }
+
+    #[test]
+    fn overflow() {
+        let commands: Vec<Cmd> = ["inc a", "cpy 2 b"].into_iter().map(Cmd::from).collect();
+
+        let mut prog: Program<Checked<i32>> =
+            Program::new([i32::MAX as i64, 0, 0, 0], commands.clone()).unwrap();
+        let err = prog.run().unwrap_err();
+        assert_eq!((Fault::Overflow, 0), (err.fault, err.pc));
+
+        let mut prog: Program<Wrapping<i32>> =
+            Program::new([i32::MAX as i64, 0, 0, 0], commands.clone()).unwrap();
+        prog.run().unwrap();
+        assert_eq!(i32::MIN, prog.registers[0]);
+
+        let mut prog: Program<Saturating<i32>> =
+            Program::new([i32::MAX as i64, 0, 0, 0], commands.clone()).unwrap();
+        prog.run().unwrap();
+        assert_eq!(i32::MAX, prog.registers[0]);
+
+        let mut prog: Program<Unbounded> =
+            Program::new([i32::MAX as i64, 0, 0, 0], commands).unwrap();
+        prog.run().unwrap();
+        assert_eq!(BigInt::from(i32::MAX) + 1, prog.registers[0]);
+    }
+
+    #[test]
+    fn decompile() {
+        // d = a + b * c, then halve a with a remainder in c
+        let commands: Vec<Cmd> = [
+            "cpy a d", "inc d", "dec b", "jnz b -2", "dec c", "jnz c -4", "jnz a 2", "jnz 1 3",
+            "dec a", "jnz 1 -3", "inc c",
+        ]
+        .into_iter()
+        .map(Cmd::from)
+        .collect();
+
+        let decompiler = Decompiler::new(&commands);
+
+        let loops: Vec<(usize, usize, usize)> = decompiler
+            .loops()
+            .iter()
+            .map(|lp| (lp.head, lp.latch, lp.height))
+            .collect();
+        assert_eq!(vec![(1, 3, 1), (6, 9, 1), (1, 5, 2)], loops);
+
+        let expected = "  0 | d = a
+    | do {
+    |     do {
+  1 |         d += 1
+  2 |         b -= 1
+  3 |     } while b != 0
+  4 |     c -= 1
+  5 | } while c != 0
+    | loop {
+  6 |     if a == 0 {
+  7 |         break
+    |     }
+  8 |     a -= 1
+  9 | }
+ 10 | c += 1
+";
+        assert_eq!(expected, decompiler.pseudocode());
+    }
 }
@@ -1,5 +1,6 @@
-use anyhow::Result;
-use std::collections::{HashMap, HashSet, VecDeque};
+use anyhow::{anyhow, bail, Result};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 
 // The puzzle input is a grid containing three types of tiles:
 // - wall: '#'
@@ -19,13 +20,6 @@ struct Position {
     col: u8,
 }
 
-impl Position {
-    /// Compute the l1-distance between two positions.
-    fn distance(&self, other: &Position) -> u32 {
-        self.row.abs_diff(other.row) as u32 + self.col.abs_diff(other.col) as u32
-    }
-}
-
 /// Represent the grid as a map: Position -> Option<u8> containing the non-wall tiles.
 struct Grid {
     tiles: HashMap<Position, Option<u8>>,
@@ -53,130 +47,241 @@ impl From<&str> for Grid {
 }
 
 impl Grid {
-    /// Find the position of the digit-tile with digit `digit` in the grid.
-    fn find(&self, digit: u8) -> Option<Position> {
-        self.tiles
+    /// List the digit-tiles of the grid with their position, sorted by digit.
+    fn digits(&self) -> Vec<(u8, Position)> {
+        let mut digits: Vec<(u8, Position)> = self
+            .tiles
             .iter()
-            .filter(|(_, &tile)| tile == Some(digit))
-            .map(|(&pos, _)| pos)
-            .next()
-    }
+            .filter_map(|(&pos, &tile)| Some((tile?, pos)))
+            .collect();
 
-    /// Encode the digit-tiles as an 8-bit integer.
-    /// The k-th bit is one if and only if the grid contains a tile with digit k.
-    fn encode_digit_tiles(&self) -> u8 {
-        self.tiles
-            .iter()
-            .filter_map(|(_, &tile)| tile)
-            .fold(0, |mut enc, digit| {
-                enc += 1 << digit;
-                enc
-            })
+        digits.sort_by_key(|&(digit, _)| digit);
+
+        digits
     }
 
     /// Find the positions one step away from `pos`.
-    fn neighbors(&self, pos: Position) -> Vec<Position> {
-        self.tiles
-            .iter()
-            .filter(|(other, _)| pos.distance(other) == 1)
-            .map(|(&pos, _)| pos)
-            .collect()
+    fn neighbors(&self, pos: Position) -> impl Iterator<Item = Position> + '_ {
+        let Position { row, col } = pos;
+
+        // the outermost tiles are walls, so the neighbors of a tile never underflow
+        [
+            (row - 1, col),
+            (row + 1, col),
+            (row, col - 1),
+            (row, col + 1),
+        ]
+        .into_iter()
+        .map(|(row, col)| Position { row, col })
+        .filter(|pos| self.tiles.contains_key(pos))
     }
-}
 
-// ----------------------------------------------
+    /// Compute the number of steps from `start` to every reachable position (BFS).
+    fn distances_from(&self, start: Position) -> HashMap<Position, u32> {
+        let mut distances: HashMap<Position, u32> = HashMap::from([(start, 0)]);
+        let mut queue: VecDeque<Position> = VecDeque::from([start]);
+
+        while let Some(pos) = queue.pop_front() {
+            let steps = distances[&pos] + 1;
+
+            for next in self.neighbors(pos) {
+                if let Entry::Vacant(entry) = distances.entry(next) {
+                    entry.insert(steps);
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Compute the number of steps between every pair of digit-tiles, with one BFS per digit.
+    /// The rows and columns of the matrix follow the order of `self.digits()`.
+    fn distance_matrix(&self) -> Result<Vec<Vec<u32>>> {
+        let digits = self.digits();
+        let mut matrix: Vec<Vec<u32>> = Vec::with_capacity(digits.len());
 
-#[derive(Eq, PartialEq, Hash, Clone)]
-struct State {
-    steps: u32, // number of steps taken so far
-    seen: u8,   // digit-nodes seen so far, encoded by bit position
+        for &(digit, start) in digits.iter() {
+            let distances = self.distances_from(start);
+
+            let row = digits
+                .iter()
+                .map(|(other, pos)| {
+                    let steps = distances.get(pos);
+                    steps
+                        .copied()
+                        .ok_or(anyhow!("no path from {digit} to {other}"))
+                })
+                .collect::<Result<Vec<u32>>>()?;
+
+            matrix.push(row);
+        }
+
+        Ok(matrix)
+    }
 }
 
 // ----------------------------------------------
 
-fn main() -> Result<()> {
-    let input: String = std::fs::read_to_string("input/day-24.txt")?;
+// With the pairwise distances between the points of interest known, finding the shortest
+// route is a travelling salesman problem, solved exactly with the Held-Karp dynamic
+// programming over subsets of points (encoded as bitmasks).
 
-    let grid = Grid::from(&input[..]);
-    let all_digits = grid.encode_digit_tiles();
+const MAX_POINTS: usize = 20;
 
-    // ----------------------------------------------
-
-    // minimal number of steps for parts 1 and 2
-    let mut min_steps_1: Option<u32> = None;
-    let mut min_steps_2: Option<u32> = None;
+/// Constraint on the last point of a tour.
+#[derive(Clone, Copy)]
+pub enum TourEnd {
+    Open,         // the tour ends anywhere
+    Closed,       // the tour returns to its start
+    Fixed(usize), // the tour ends on a given point
+}
 
-    // keep track of the states in which each position was visited
-    let mut visited: HashMap<Position, HashSet<State>> = HashMap::new();
+/// Visiting order of the points and total number of steps of a tour.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Tour {
+    pub order: Vec<usize>,
+    pub steps: u32,
+}
 
-    let position = grid.find(0).unwrap(); // starting position = position of tile 0
-    let state = State { steps: 0, seen: 0 }; // starting state
+/// Find the shortest tour starting at `start` and visiting all points at least once.
+pub fn shortest_tour(dist: &[Vec<u32>], start: usize, end: TourEnd) -> Result<Tour> {
+    let n = dist.len();
 
-    // initialize the queue with the starting (position, state) pair
-    let mut queue: VecDeque<(Position, State)> = VecDeque::from([(position, state)]);
+    if n > MAX_POINTS {
+        bail!("too many points of interest: {n} > {MAX_POINTS}");
+    }
 
-    // ----------------------------------------------
+    if start >= n {
+        bail!("invalid starting point: {start}");
+    }
 
-    // Use depth-first search to find the shortest paths through all digit tiles
-    // and back to the start.
-    // Because DFS visits the states ordered by ascending step count, the first
-    // path we find through all digits (and through all digits and back to 0) is
-    // necessarly the shortest.
-    while let Some((position, mut state)) = queue.pop_front() {
-        // update the seen digits for the current state
-        if let Some(digit) = grid.tiles.get(&position).unwrap() {
-            state.seen = state.seen | (1 << digit); // set digit bit to one
+    if let TourEnd::Fixed(end) = end {
+        if end >= n || (end == start && n > 1) {
+            bail!("invalid end point: {end}");
         }
+    }
 
-        // check if the current position has already been visited with a better state,
-        // i.e. a state with (at least) the same digits seen and a lower step count
-        if let Some(set) = visited.get(&position) {
-            let better_states = set
-                .iter()
-                .filter(|&other| other.steps <= state.steps)
-                .filter(|&other| (other.seen & state.seen) == state.seen);
+    // cost[mask * n + last] = steps of the shortest path from `start` visiting the points
+    // in `mask` and ending at `last`, parent[mask * n + last] = point visited before `last`
+    let full: usize = (1 << n) - 1;
+    let mut cost: Vec<u32> = vec![u32::MAX; (full + 1) * n];
+    let mut parent: Vec<u8> = vec![u8::MAX; (full + 1) * n];
 
-            // drop the branch if we have visited the position with a better state
-            if better_states.count() > 0 {
-                continue;
-            }
-        }
+    cost[(1 << start) * n + start] = 0;
 
-        // add the current state to the set of visited states for the current position
-        visited
-            .entry(position)
-            .or_insert(HashSet::new())
-            .insert(state.clone());
+    for mask in (1..=full).filter(|mask| mask & (1 << start) != 0) {
+        for last in (0..n).filter(|last| mask & (1 << last) != 0) {
+            let steps = cost[mask * n + last];
 
-        if state.seen == all_digits {
-            // set the value for part 1 if it has not yet been found
-            if min_steps_1.is_none() {
-                min_steps_1 = Some(state.steps);
+            if steps == u32::MAX {
+                continue;
             }
 
-            // if we are on tile 0, exit the loop
-            if let Some(0) = grid.tiles.get(&position).unwrap() {
-                min_steps_2 = Some(state.steps);
-                break;
+            for next in (0..n).filter(|next| mask & (1 << next) == 0) {
+                let idx = (mask | (1 << next)) * n + next;
+                let next_steps = steps + dist[last][next];
+
+                if next_steps < cost[idx] {
+                    cost[idx] = next_steps;
+                    parent[idx] = last as u8;
+                }
             }
         }
+    }
 
-        // add neighbors to the back of the queue
-        for next_position in grid.neighbors(position) {
-            let mut next_state = state.clone();
-            next_state.steps += 1;
+    // choose the last point of the tour according to the end constraint
+    let (last, steps) = (0..n)
+        .filter(|&last| match end {
+            TourEnd::Fixed(end) => last == end,
+            _ => true,
+        })
+        .map(|last| match end {
+            TourEnd::Closed => (last, cost[full * n + last] + dist[last][start]),
+            _ => (last, cost[full * n + last]),
+        })
+        .min_by_key(|&(_, steps)| steps)
+        .expect("there is at least one point");
+
+    // walk back through the parents to recover the visiting order
+    let mut order: Vec<usize> = vec![last];
+    let (mut mask, mut last) = (full, last);
+
+    while last != start {
+        let prev = parent[mask * n + last] as usize;
+        mask ^= 1 << last;
+        last = prev;
+        order.push(last);
+    }
 
-            queue.push_back((next_position, next_state));
-        }
+    order.reverse();
+
+    if let TourEnd::Closed = end {
+        order.push(start);
     }
 
-    // ----------------------------------------------
+    Ok(Tour { order, steps })
+}
+
+// ----------------------------------------------
+
+fn main() -> Result<()> {
+    let input: String = std::fs::read_to_string("input/day-24.txt")?;
 
-    let part_1 = min_steps_1.unwrap();
-    let part_2 = min_steps_2.unwrap();
+    let grid = Grid::from(&input[..]);
+    let digits = grid.digits();
+    let dist = grid.distance_matrix()?;
+
+    // the robot starts on the tile 0, which comes first in the sorted digits
+    if digits.first().map(|&(digit, _)| digit) != Some(0) {
+        bail!("no starting tile 0");
+    }
+
+    let tour_1 = shortest_tour(&dist, 0, TourEnd::Open)?;
+    let tour_2 = shortest_tour(&dist, 0, TourEnd::Closed)?;
+
+    let part_1 = tour_1.steps;
+    let part_2 = tour_2.steps;
 
     dbg!(part_1);
     dbg!(part_2);
 
+    let order: Vec<u8> = tour_2.order.iter().map(|&idx| digits[idx].0).collect();
+    println!("visiting order: {order:?}");
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "###########
+#0.1.....2#
+#.#######.#
+#4.......3#
+###########";
+
+    #[test]
+    fn part_1() {
+        let grid = Grid::from(INPUT);
+        let dist = grid.distance_matrix().unwrap();
+        let tour = shortest_tour(&dist, 0, TourEnd::Open).unwrap();
+
+        assert_eq!(14, tour.steps);
+        assert_eq!(vec![0, 4, 1, 2, 3], tour.order);
+    }
+
+    #[test]
+    fn fixed_end() {
+        let grid = Grid::from(INPUT);
+        let dist = grid.distance_matrix().unwrap();
+
+        let tour = shortest_tour(&dist, 0, TourEnd::Closed).unwrap();
+        assert_eq!(20, tour.steps);
+
+        let tour = shortest_tour(&dist, 0, TourEnd::Fixed(4)).unwrap();
+        assert_eq!(vec![0, 1, 2, 3, 4], tour.order);
+        assert_eq!(18, tour.steps);
+    }
+}
@@ -1,141 +1,228 @@
-use std::cmp::{max, min};
+// The grid is a chain of concentric squares, each included in the next, which are
+// filled in a spiral pattern starting at the origin (position (0, 0), value 1).
+//
+// The square k contains the tiles with sup-norm k, and ends at its bottom right corner
+// (k, -k) with the value (2k + 1)^2. It starts one tile above that corner and follows
+// the edges of the square counterclockwise. Counting backwards from the corner gives
+// the position of a value directly, without walking the spiral, and conversely.
+
+type Position = (i64, i64);
+
+/// Returns the position of the tile with the value `value` (starting from 1).
+fn position(value: u64) -> Position {
+    assert!(value > 0, "the spiral starts at 1");
+
+    let k = (value - 1).isqrt().div_ceil(2); // square containing the value
+    let back = ((2 * k + 1).pow(2) - value) as i64; // steps back from the last corner
+    let k = k as i64;
+
+    match back / (2 * k).max(1) {
+        0 => (k - back, -k),            // bottom edge
+        1 => (-k, -k + (back - 2 * k)), // left edge
+        2 => (-k + (back - 4 * k), k),  // top edge
+        _ => (k, k - (back - 6 * k)),   // right edge
+    }
+}
 
-#[derive(Clone)]
-struct Vector {
-    x: i32,
-    y: i32,
+/// Returns the value of the tile at position `pos`.
+fn value(pos: Position) -> u64 {
+    let (x, y) = pos;
+    let k = x.abs().max(y.abs()); // square containing the position
+
+    // steps back from the last corner
+    let back = if y == -k {
+        k - x // bottom edge
+    } else if x == -k {
+        2 * k + (y + k) // left edge
+    } else if y == k {
+        4 * k + (x + k) // top edge
+    } else {
+        6 * k + (k - y) // right edge
+    };
+
+    (2 * k as u64 + 1).pow(2) - back as u64
 }
 
-impl Vector {
-    fn add(&self, other: &Self) -> Self {
+/// Rule computing the value of a tile as a weighted sum of its neighbours.
+struct Neighbourhood {
+    weights: Vec<(Position, u64)>, // offsets of the neighbours with their weights
+}
+
+impl Neighbourhood {
+    /// The four adjacent tiles.
+    fn von_neumann() -> Self {
+        let offsets = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
         Self {
-            x: self.x + other.x,
-            y: self.y + other.y,
+            weights: offsets.into_iter().map(|off| (off, 1)).collect(),
         }
     }
 
-    fn rotate_left(&self) -> Self {
+    /// The eight adjacent tiles, including diagonals.
+    fn moore() -> Self {
+        let offsets = (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)));
+
         Self {
-            x: -self.y,
-            y: self.x,
+            weights: offsets
+                .filter(|&off| off != (0, 0))
+                .map(|off| (off, 1))
+                .collect(),
         }
     }
 
-    /// Returns the sup-norm (l0-norm) of the vector,
-    fn sup_norm(&self) -> u32 {
-        max(self.x.abs(), self.y.abs()) as u32
+    fn weighted(weights: Vec<(Position, u64)>) -> Self {
+        Self { weights }
     }
 }
 
-/// An iterator which walks the grid in a spiral pattern.
-///
-/// The grid is composed of a chain of concentric squares, each included in the next.
-/// The walker starts at the origin (position (0, 0), value 1).
+/// An iterator which fills the spiral with the sums given by a neighbourhood rule,
+/// starting with the value 1 at the origin.
 ///
-/// It enters a square one tile above the square's bottom right corner and visits
-/// all tiles following the edges of the square counterclockwise.
-/// Once all tiles have been visited (when reaching the bottom right corner),
-/// it takes a step to the right to enter the next square.
-struct GridWalker {
-    /// Current position on the grid
-    position: Vector,
-
-    /// Vector to add to the position to take the next step
-    step_delta: Vector,
+/// Only the tiles filled so far are stored, so the grid grows with the spiral.
+struct SpiralFiller {
+    rule: Neighbourhood,
+    tiles: Vec<u64>, // values of the tiles filled so far, in the order of the spiral
+    next: u64,       // index of the next tile in the spiral
 }
 
-impl GridWalker {
-    fn new() -> Self {
+impl SpiralFiller {
+    fn new(rule: Neighbourhood) -> Self {
         Self {
-            position: Vector { x: 0, y: 0 },
-            step_delta: Vector { x: 1, y: 0 },
+            rule,
+            tiles: Vec::new(),
+            next: 1,
         }
     }
 }
 
-impl Iterator for GridWalker {
-    type Item = Vector;
+impl Iterator for SpiralFiller {
+    type Item = u64;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // Compute the sup-norm of the current position.
-        // Note that all tiles in a square have the same sup-norm.
-        let sup_norm = self.position.sup_norm();
-
-        // take a step
-        self.position = self.position.add(&self.step_delta);
-
-        // Check whether we have reached a corner of the current square.
-        // The corners are at the four positions (x, y) with |x| = |y| = sup_norm.
-        let corner_reached = min(self.position.x.abs(), self.position.y.abs()) == sup_norm as i32;
-
-        // check whether we are in the bottom right quadrant
-        let in_bottom_right = (self.position.x > 0) && (self.position.y < 0);
-
-        // check whether we have stepped into the next square
-        let entered_next_square = self.position.sup_norm() > sup_norm;
-
-        // turn left at any but the bottom right corner or after entering a new square
-        if (corner_reached && !in_bottom_right) || entered_next_square {
-            self.step_delta = self.step_delta.rotate_left();
-        }
-
-        Some(self.position.clone())
+        let (x, y) = position(self.next);
+
+        // sum the values of the neighbours, where the unvisited tiles have value 0
+        let val = match self.next {
+            1 => 1,
+            _ => self
+                .rule
+                .weights
+                .iter()
+                .map(|&((dx, dy), weight)| {
+                    let idx = value((x + dx, y + dy)) as usize - 1;
+                    weight * self.tiles.get(idx).copied().unwrap_or(0)
+                })
+                .sum(),
+        };
+
+        self.tiles.push(val);
+        self.next += 1;
+
+        Some(val)
     }
 }
 
 fn main() {
     let input = std::fs::read_to_string("input.txt").unwrap();
-    let input: u32 = input.trim().parse().unwrap();
+    let input: u64 = input.trim().parse().unwrap();
 
     // --- Part One --- //
 
-    let mut walker = GridWalker::new();
-
-    // take input - 1 steps starting at the origin (value = 1)
-    // to get to value = input
-    for _ in 1..input {
-        let _ = walker.next();
-    }
+    let (x, y) = position(input);
 
-    let part_one = walker.position.x.abs() + walker.position.y.abs();
+    let part_one = x.abs() + y.abs();
 
     println!("Part One: {}", part_one);
 
     // --- Part Two --- //
 
-    // Create a grid large enough to contain the target tile
-    // by taking the grid containing the target tile from part one.
-    // Note that this grid is much larger than it needs to be.
-    let origin_idx = walker.position.sup_norm() as usize;
-    let side_length = 2 * origin_idx + 1;
-    let mut grid = vec![vec![0; side_length]; side_length];
+    let mut filler = SpiralFiller::new(Neighbourhood::moore());
 
-    grid[origin_idx][origin_idx] = 1; // set origin value
+    let part_two = filler.find(|&val| val > input).unwrap();
 
-    let mut walker = GridWalker::new();
+    println!("Part Two: {}", part_two);
+
+    // the same search with other rules, if asked for
+    if std::env::args().nth(1).as_deref() != Some("--rules") {
+        return;
+    }
+
+    let mut filler = SpiralFiller::new(Neighbourhood::von_neumann());
+
+    println!(
+        "4-neighbourhood: {}",
+        filler.find(|&val| val > input).unwrap()
+    );
+
+    // diagonal neighbours count twice
+    let weights = Neighbourhood::moore()
+        .weights
+        .into_iter()
+        .map(|((dx, dy), _)| ((dx, dy), if dx != 0 && dy != 0 { 2 } else { 1 }))
+        .collect();
+
+    let mut filler = SpiralFiller::new(Neighbourhood::weighted(weights));
+
+    println!(
+        "Weighted 8-neighbourhood: {}",
+        filler.find(|&val| val > input).unwrap()
+    );
+}
 
-    let part_two = loop {
-        let position = walker.next().unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // get the indices for the current tile in the grid
-        let row_idx = (position.x + origin_idx as i32) as usize;
-        let col_idx = (position.y + origin_idx as i32) as usize;
+    #[test]
+    fn inverse() {
+        // the two mappings are inverse of each other
+        assert!((1..10_000).all(|val| value(position(val)) == val));
 
-        // comput the value of the current tile by summing all neighbors
-        // all unvisited tiles have value 0 (including the current tile)
-        let mut value = 0;
-        for row_off in 0..3 {
-            for col_off in 0..3 {
-                value += grid[row_idx + row_off - 1][col_idx + col_off - 1];
+        for x in -50..=50 {
+            for y in -50..=50 {
+                assert_eq!((x, y), position(value((x, y))));
             }
         }
 
-        grid[row_idx][col_idx] = value;
+        assert_eq!(1, value((0, 0)));
+        assert_eq!(12, value((2, 1)));
+        assert_eq!(23, value((0, -2)));
+    }
 
-        if value > input {
-            break value;
+    #[test]
+    fn distances() {
+        for (val, distance) in [(1, 0), (12, 3), (23, 2), (1024, 31)] {
+            let (x, y) = position(val);
+            assert_eq!(distance, x.abs() + y.abs());
         }
-    };
+    }
 
-    println!("Part Two: {}", part_two);
+    #[test]
+    fn moore() {
+        let expected = [
+            1, 1, 2, 4, 5, 10, 11, 23, 25, 26, 54, 57, 59, 122, 133, 142, 147, 304,
+        ];
+        let values: Vec<u64> = SpiralFiller::new(Neighbourhood::moore()).take(18).collect();
+
+        assert_eq!(expected[..], values);
+    }
+
+    #[test]
+    fn other_rules() {
+        let values: Vec<u64> = SpiralFiller::new(Neighbourhood::von_neumann())
+            .take(10)
+            .collect();
+        assert_eq!(vec![1, 1, 1, 2, 2, 3, 3, 4, 5, 5], values);
+
+        // diagonal neighbours count twice
+        let weights = Neighbourhood::moore()
+            .weights
+            .into_iter()
+            .map(|((dx, dy), _)| ((dx, dy), if dx != 0 && dy != 0 { 2 } else { 1 }))
+            .collect();
+        let values: Vec<u64> = SpiralFiller::new(Neighbourhood::weighted(weights))
+            .take(4)
+            .collect();
+        assert_eq!(vec![1, 1, 3, 6], values);
+    }
 }
@@ -1,163 +1,317 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::fmt;
 
-struct NodeData {
+/// Largest tower drawn after the answers, as bigger ones do not fit on a screen.
+const MAX_RENDERED_NODES: usize = 20;
+
+/// Index of a node in the arena of the tree.
+type NodeId = usize;
+
+struct Node {
+    name: String,
     weight: u32,
-    children: Vec<String>,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    total: u32, // weight of the subtree rooted at the node
+}
+
+/// Errors found in the input.
+#[derive(Debug)]
+enum TreeError {
+    Parse(String),                              // line which could not be parsed
+    Duplicate(String),                          // node defined twice
+    Dangling { parent: String, child: String }, // child which is not defined
+    MultipleParents(String),                    // child of several nodes
+    MultipleRoots(Vec<String>),
+    Cycle(Vec<String>), // nodes not reachable from the root
+    Unfixable(String),  // imbalanced node which no single weight change balances
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::Parse(line) => write!(f, "invalid line: {}", line),
+            TreeError::Duplicate(name) => write!(f, "node {} is defined twice", name),
+            TreeError::Dangling { parent, child } => {
+                write!(f, "child {} of {} is not defined", child, parent)
+            }
+            TreeError::MultipleParents(name) => write!(f, "node {} has several parents", name),
+            TreeError::MultipleRoots(names) => write!(f, "several roots: {}", names.join(", ")),
+            TreeError::Cycle(names) => write!(f, "cycle through: {}", names.join(", ")),
+            TreeError::Unfixable(name) => {
+                write!(f, "node {} cannot be balanced by changing one weight", name)
+            }
+        }
+    }
 }
 
 /// Parses one line of the input.
-/// Returns the name and data of the node defined by the line.
-fn parse_input_line(line: &str) -> (String, NodeData) {
+/// Returns the name, weight and children of the node defined by the line.
+fn parse_input_line(line: &str) -> Result<(&str, u32, Vec<&str>), TreeError> {
+    let error = || TreeError::Parse(line.to_string());
+
     let mut iter = line.split_whitespace();
 
-    let name = iter.next().unwrap().to_string();
+    let name = iter.next().ok_or_else(error)?;
 
     let weight = iter
         .next()
-        .unwrap()
-        .trim_matches(['(', ')'])
-        .parse()
-        .unwrap();
-
-    let _ = iter.next(); // skip "->"
-
-    let children = iter.fold(Vec::new(), |mut vec, s| {
-        vec.push(s.trim_end_matches(',').to_string());
-        vec
-    });
-
-    (name, NodeData { weight, children })
+        .and_then(|s| s.strip_prefix('('))
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(error)?;
+
+    let children = match iter.next() {
+        Some("->") => iter.map(|s| s.trim_end_matches(',')).collect(),
+        Some(_) => return Err(error()),
+        None => Vec::new(),
+    };
+
+    Ok((name, weight, children))
 }
 
-/// Structure containing the result of evaluating a subtree.
-///
-/// A subtree is balanced if all of its children subtrees have the same weight.
-/// For a balanced subtree, the `value` contains the weight of the subtree,
-/// which is the sum of the weight of the subtree root and the weights of all
-/// children subtrees.
-/// For an imbalanced subtree, the value contains the weight that the (unique)
-/// imbalanced node should have to make the subtree balanced.
-/// The imbalanced node is the node causing the imbalance, it is itself balanced.
-struct SubTreeResult {
-    is_balanced: bool,
-    value: u32,
+/// Imbalance of a node, whose children subtrees do not all have the same weight.
+#[derive(Debug)]
+struct Imbalance {
+    node: NodeId,
+    expected: Option<u32>, // weight of most children subtrees, `None` on a tie
+    corrections: Vec<(NodeId, u32)>, // weight each odd child should have
 }
 
 struct Tree {
-    nodes: HashMap<String, NodeData>,
+    nodes: Vec<Node>,
+    root: NodeId,
+    order: Vec<NodeId>, // nodes in breadth-first order from the root
 }
 
 impl Tree {
-    /// Finds the root of the tree.
-    fn root(&self) -> &str {
-        let nodes: HashSet<_> = self.nodes.keys().collect();
-        let nodes_with_parent = self.nodes.values().fold(HashSet::new(), |mut set, data| {
-            set.extend(data.children.iter());
-            set
-        });
+    fn parse(input: &str) -> Result<Self, TreeError> {
+        let lines = input
+            .lines()
+            .map(parse_input_line)
+            .collect::<Result<Vec<_>, _>>()?;
 
-        nodes.difference(&nodes_with_parent).next().unwrap()
-    }
+        let mut ids: HashMap<&str, NodeId> = HashMap::new();
+        let mut nodes = Vec::with_capacity(lines.len());
 
-    /// Evaluates a subtree recursively.
-    fn evaluate_subtree(&self, node: &str) -> SubTreeResult {
-        let data = self.nodes.get(node).unwrap();
+        for (name, weight, _) in &lines {
+            if ids.insert(name, nodes.len()).is_some() {
+                return Err(TreeError::Duplicate(name.to_string()));
+            }
 
-        // A leaf is always balanced and the weight of its subtree is the leaf weight.
-        if data.children.is_empty() {
-            return SubTreeResult {
-                is_balanced: true,
-                value: data.weight,
-            };
+            nodes.push(Node {
+                name: name.to_string(),
+                weight: *weight,
+                parent: None,
+                children: Vec::new(),
+                total: 0,
+            });
         }
 
-        // compute the SubTreeResult for all children nodes
-        let mut children_res = Vec::with_capacity(data.children.len());
+        // link the nodes
+        for (id, (name, _, children)) in lines.iter().enumerate() {
+            for child in children {
+                let Some(&child_id) = ids.get(child) else {
+                    return Err(TreeError::Dangling {
+                        parent: name.to_string(),
+                        child: child.to_string(),
+                    });
+                };
+
+                if nodes[child_id].parent.replace(id).is_some() {
+                    return Err(TreeError::MultipleParents(child.to_string()));
+                }
+
+                nodes[id].children.push(child_id);
+            }
+        }
 
-        for child in data.children.iter() {
-            let child_res = self.evaluate_subtree(child);
+        let roots: Vec<NodeId> = (0..nodes.len())
+            .filter(|&id| nodes[id].parent.is_none())
+            .collect();
 
-            // if we have already found the imbalanced node, propagate the result
-            // all the way up the call stack
-            if !child_res.is_balanced {
-                return child_res;
+        let root = match roots[..] {
+            [root] => root,
+            [] => {
+                let names = nodes.iter().map(|node| node.name.clone()).collect();
+                return Err(TreeError::Cycle(names));
+            }
+            _ => {
+                let names = roots.iter().map(|&id| nodes[id].name.clone()).collect();
+                return Err(TreeError::MultipleRoots(names));
             }
+        };
+
+        // the nodes which cannot be reached from the root are on a cycle (or below one)
+        let mut order = vec![root];
+        let mut idx = 0;
+
+        while idx < order.len() {
+            order.extend(nodes[order[idx]].children.iter().copied());
+            idx += 1;
+        }
+
+        if order.len() < nodes.len() {
+            let mut reached = vec![false; nodes.len()];
+            order.iter().for_each(|&id| reached[id] = true);
+
+            let names = (0..nodes.len())
+                .filter(|&id| !reached[id])
+                .map(|id| nodes[id].name.clone())
+                .collect();
 
-            children_res.push(child_res);
+            return Err(TreeError::Cycle(names));
         }
 
-        // count the number of occurences of each weight amongst the children
-        let weight_count = children_res.iter().fold(HashMap::new(), |mut map, res| {
-            *map.entry(res.value).or_insert(0) += 1;
-            map
-        });
-
-        // If the subtree is balanced (all children subtrees have the same weight),
-        // return the weight of the subtree
-        if weight_count.keys().len() == 1 {
-            let (weight, count) = weight_count.into_iter().next().unwrap();
-
-            return SubTreeResult {
-                is_balanced: true,
-                value: data.weight + weight * count,
-            };
+        // compute the weights of the subtrees, children first
+        for &id in order.iter().rev() {
+            let children: u32 = nodes[id].children.iter().map(|&c| nodes[c].total).sum();
+            nodes[id].total = nodes[id].weight + children;
         }
 
-        // At this point, not all children subtrees have the same weight.
+        Ok(Self { nodes, root, order })
+    }
+
+    fn root(&self) -> &str {
+        &self.nodes[self.root].name
+    }
+
+    /// Returns the imbalance of a node, if any.
+    fn imbalance(&self, id: NodeId) -> Option<Imbalance> {
+        let children = &self.nodes[id].children;
+
+        // count the number of occurences of each weight amongst the children subtrees
+        let mut weight_count: HashMap<u32, usize> = HashMap::new();
+
+        for &child in children {
+            *weight_count.entry(self.nodes[child].total).or_default() += 1;
+        }
 
-        // There are two distinct weight values.
-        // One value appears only once (the minority weight) and is the
-        // weight of the child node that needs adjusting to balance the
-        // current node. All other child nodes have the same weight
-        // (the majority weight).
+        if weight_count.len() <= 1 {
+            return None;
+        }
 
-        let majority_weight: u32 = *weight_count
+        // the weight of most children, if there is one
+        let max_count = *weight_count.values().max().unwrap();
+        let mut majority = weight_count
             .iter()
-            .max_by_key(|&(_, count)| count)
-            .unwrap()
-            .0;
+            .filter(|&(_, &count)| count == max_count);
+
+        let expected = match (majority.next(), majority.next()) {
+            (Some((&weight, _)), None) => Some(weight),
+            _ => None,
+        };
+
+        // change the weight of each odd child by the difference of the subtree weights,
+        // unless the child is too light to be corrected
+        let corrections = match expected {
+            Some(expected) => children
+                .iter()
+                .filter(|&&child| self.nodes[child].total != expected)
+                .filter_map(|&child| {
+                    let node = &self.nodes[child];
+                    Some((child, (node.weight + expected).checked_sub(node.total)?))
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Some(Imbalance {
+            node: id,
+            expected,
+            corrections,
+        })
+    }
+
+    /// Returns the names of the nodes from the root down to the node `id`.
+    fn path(&self, id: NodeId) -> Vec<&str> {
+        let mut path = vec![self.nodes[id].name.as_str()];
+        let mut node = &self.nodes[id];
+
+        while let Some(parent) = node.parent {
+            node = &self.nodes[parent];
+            path.push(&node.name);
+        }
+
+        path.reverse();
+        path
+    }
 
-        let minority_weight: u32 = *weight_count
+    /// Returns every imbalanced node, deepest first.
+    ///
+    /// A wrong weight makes all the ancestors of the node imbalanced, so the corrections
+    /// of the deepest imbalance are the ones which balance the whole tower.
+    fn imbalances(&self) -> Vec<Imbalance> {
+        self.order
             .iter()
-            .min_by_key(|&(_, count)| count)
-            .unwrap()
-            .0;
+            .rev()
+            .filter_map(|&id| self.imbalance(id))
+            .collect()
+    }
+
+    /// Returns the odd node of the deepest imbalance with the weight it should have,
+    /// or `None` if the tower is balanced.
+    fn correction(&self) -> Result<Option<(NodeId, u32)>, TreeError> {
+        let Some(imbalance) = self.imbalances().into_iter().next() else {
+            return Ok(None);
+        };
 
-        // Find the node which needs adjusting. This is the node
-        // whose weight is the minority weight.
-        let (minority_idx, _) = children_res
+        // a single weight change only balances the node if exactly one child is odd
+        let odd = self.nodes[imbalance.node]
+            .children
             .iter()
-            .enumerate()
-            .find(|(_, res)| res.value == minority_weight)
-            .unwrap();
+            .filter(|&&child| Some(self.nodes[child].total) != imbalance.expected)
+            .count();
+
+        match (odd, &imbalance.corrections[..]) {
+            (1, &[correction]) => Ok(Some(correction)),
+            _ => Err(TreeError::Unfixable(
+                self.nodes[imbalance.node].name.clone(),
+            )),
+        }
+    }
 
-        let imbalanced_child = &data.children[minority_idx];
+    /// Renders the tower as an indented tree, with the weights of the nodes
+    /// and of the subtrees, and the corrections of the deepest imbalance.
+    fn render(&self) -> String {
+        let corrections: HashMap<NodeId, u32> = self
+            .imbalances()
+            .first()
+            .map(|imbalance| imbalance.corrections.iter().copied().collect())
+            .unwrap_or_default();
+
+        let mut rendered = String::new();
+        let mut stack = vec![(self.root, 0)];
+
+        while let Some((id, depth)) = stack.pop() {
+            let node = &self.nodes[id];
+
+            rendered.push_str(&format!(
+                "{}{} ({}) [{}]",
+                "  ".repeat(depth),
+                node.name,
+                node.weight,
+                node.total
+            ));
+
+            if let Some(weight) = corrections.get(&id) {
+                rendered.push_str(&format!(" <- should weigh {}", weight));
+            }
 
-        // Find the weight of the imbalanced child node and update it to match
-        // the weight of the other child nodes.
-        let imbalanced_weight = self.nodes.get(imbalanced_child).unwrap().weight as i32;
-        let weight_delta = majority_weight as i32 - minority_weight as i32;
+            rendered.push('\n');
 
-        SubTreeResult {
-            is_balanced: false,
-            value: (imbalanced_weight + weight_delta) as u32,
+            // push in reverse to render the children in order
+            stack.extend(node.children.iter().rev().map(|&child| (child, depth + 1)));
         }
+
+        rendered
     }
 }
 
 fn main() {
     let input = std::fs::read_to_string("input.txt").unwrap();
 
-    let nodes =
-        input
-            .lines()
-            .map(parse_input_line)
-            .fold(HashMap::new(), |mut map, (name, data)| {
-                map.insert(name, data);
-                map
-            });
-
-    let tree = Tree { nodes };
+    let tree = Tree::parse(&input).unwrap_or_else(|err| panic!("{}", err));
 
     // --- Part One --- //
 
@@ -167,9 +321,134 @@ fn main() {
 
     // --- Part Two --- //
 
-    let result = tree.evaluate_subtree(tree.root());
+    let imbalances = tree.imbalances();
+
+    for imbalance in &imbalances {
+        let name = tree.path(imbalance.node).join(" -> ");
+
+        match imbalance.expected {
+            Some(expected) => {
+                let corrections: Vec<(&str, u32)> = imbalance
+                    .corrections
+                    .iter()
+                    .map(|&(child, weight)| (tree.nodes[child].name.as_str(), weight))
+                    .collect();
+
+                println!(
+                    "{} is imbalanced: children subtrees should weigh {}, corrections {:?}",
+                    name, expected, corrections
+                );
+            }
+            None => println!("{} is imbalanced, with no majority weight", name),
+        }
+    }
+
+    // the deepest imbalance is the one to correct
+    match tree.correction().unwrap_or_else(|err| panic!("{}", err)) {
+        Some((_, part_two)) => println!("Part Two: {}", part_two),
+        None => println!("Part Two: the tower is balanced"),
+    }
+
+    if tree.nodes.len() <= MAX_RENDERED_NODES {
+        print!("{}", tree.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+pbga (66)
+xhth (57)
+ebii (61)
+havc (66)
+ktlj (57)
+fwft (72) -> ktlj, cntj, xhth
+qoyq (66)
+padx (45) -> pbga, havc, qoyq
+tknk (41) -> ugml, padx, fwft
+jptl (61)
+ugml (68) -> gyxo, ebii, jptl
+gyxo (61)
+cntj (57)";
+
+    #[test]
+    fn example() {
+        let tree = Tree::parse(EXAMPLE).unwrap();
+
+        assert_eq!("tknk", tree.root());
+
+        let (node, weight) = tree.correction().unwrap().unwrap();
+        assert_eq!(("ugml", 60), (tree.nodes[node].name.as_str(), weight));
+    }
+
+    #[test]
+    fn balanced() {
+        let tree = Tree::parse("a (1) -> b, c\nb (2)\nc (2)").unwrap();
+
+        assert!(tree.imbalances().is_empty());
+        assert_eq!(None, tree.correction().unwrap());
+    }
+
+    #[test]
+    fn tie() {
+        // two children with different weights: no majority
+        let tree = Tree::parse("a (1) -> b, c\nb (2)\nc (3)").unwrap();
 
-    let part_two = result.value;
+        assert_eq!(None, tree.imbalances()[0].expected);
+        assert!(matches!(tree.correction(), Err(TreeError::Unfixable(name)) if name == "a"));
+    }
+
+    #[test]
+    fn too_light() {
+        // the light child would need a negative weight of its own
+        let tree = Tree::parse("a (1) -> b, c, d\nb (9)\nc (9)\nd (1) -> e\ne (10)").unwrap();
+
+        assert!(matches!(tree.correction(), Err(TreeError::Unfixable(name)) if name == "a"));
+    }
+
+    #[test]
+    fn ambiguous() {
+        // two children differ from the majority: no single weight change fixes both
+        let tree = Tree::parse("a (1) -> b, c, d, e\nb (2)\nc (2)\nd (3)\ne (4)").unwrap();
 
-    println!("Part Two: {}", part_two);
+        assert_eq!(2, tree.imbalances()[0].corrections.len());
+        assert!(matches!(tree.correction(), Err(TreeError::Unfixable(name)) if name == "a"));
+    }
+
+    #[test]
+    fn render() {
+        let tree = Tree::parse("a (1) -> b, c, d\nb (2)\nc (2) -> e\nd (3)\ne (0)").unwrap();
+
+        let expected = "\
+a (1) [8]
+  b (2) [2]
+  c (2) [2]
+    e (0) [0]
+  d (3) [3] <- should weigh 2
+";
+        assert_eq!(expected, tree.render());
+    }
+
+    #[test]
+    fn errors() {
+        assert!(matches!(Tree::parse("a 1"), Err(TreeError::Parse(_))));
+        assert!(matches!(
+            Tree::parse("a (1)\na (2)"),
+            Err(TreeError::Duplicate(_))
+        ));
+        assert!(matches!(
+            Tree::parse("a (1) -> b"),
+            Err(TreeError::Dangling { .. })
+        ));
+        assert!(matches!(
+            Tree::parse("a (1)\nb (1)"),
+            Err(TreeError::MultipleRoots(_))
+        ));
+        assert!(matches!(
+            Tree::parse("a (1) -> b\nb (1) -> a"),
+            Err(TreeError::Cycle(_))
+        ));
+    }
 }
@@ -0,0 +1,113 @@
+use std::fmt;
+
+// A small language for the register instructions. Each line of a program is a statement
+//     register op operand [if condition]
+// where the operand is an integer or a register, and the condition combines comparisons
+// of operands with `and`, `or`, `not` and parentheses. All the registers start at 0.
+//
+// The source goes through the lexer (text to tokens), the parser (tokens to AST) and
+// then the machine (execution of the AST). Errors point back to the source with spans.
+
+// The lexer module splits the source into tokens.
+pub mod lexer;
+
+// The ast module contains the syntax tree of the programs.
+pub mod ast;
+
+// The parser module builds the syntax tree from the tokens.
+pub mod parser;
+
+// The machine module executes the programs and records the registers.
+pub mod machine;
+
+/// Range of bytes of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the smallest span containing both spans.
+    pub fn join(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnexpectedChar(char),
+    UnknownOperator(String),
+    InvalidInteger(String),
+    Expected(&'static str), // what the parser expected at the span
+    DivisionByZero,
+    Overflow,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character '{}'", ch),
+            ErrorKind::UnknownOperator(op) => write!(f, "unknown operator '{}'", op),
+            ErrorKind::InvalidInteger(int) => write!(f, "invalid integer '{}'", int),
+            ErrorKind::Expected(what) => write!(f, "expected {}", what),
+            ErrorKind::DivisionByZero => write!(f, "division by zero"),
+            ErrorKind::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+/// Error in a program, at a span of its source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub span: Span,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// Renders the error with its position in the source,
+    /// followed by the line of the source with the span underlined.
+    pub fn render(&self, src: &str) -> String {
+        let start = self.span.start.min(src.len());
+        let line_start = src[..start].rfind('\n').map_or(0, |idx| idx + 1);
+        let line_end = src[start..].find('\n').map_or(src.len(), |idx| start + idx);
+
+        let line = src[..start].matches('\n').count() + 1;
+        let column = src[line_start..start].chars().count() + 1;
+
+        let width = src[start..self.span.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        format!(
+            "line {}, column {}: {}\n{}\n{}{}",
+            line,
+            column,
+            self.kind,
+            &src[line_start..line_end],
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at bytes {}..{}",
+            self.kind, self.span.start, self.span.end
+        )
+    }
+}
+
+impl std::error::Error for Error {}
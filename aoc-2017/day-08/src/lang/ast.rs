@@ -0,0 +1,92 @@
+use super::Span;
+
+/// Comparison operator of a condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl CmpOp {
+    pub fn from_symbol(symbol: &str) -> Option<Self> {
+        match symbol {
+            "<" => Some(CmpOp::Lt),
+            "<=" => Some(CmpOp::Le),
+            ">" => Some(CmpOp::Gt),
+            ">=" => Some(CmpOp::Ge),
+            "==" => Some(CmpOp::Eq),
+            "!=" => Some(CmpOp::Ne),
+            _ => None,
+        }
+    }
+
+    pub fn apply(&self, a: i64, b: i64) -> bool {
+        match self {
+            CmpOp::Lt => a < b,
+            CmpOp::Le => a <= b,
+            CmpOp::Gt => a > b,
+            CmpOp::Ge => a >= b,
+            CmpOp::Eq => a == b,
+            CmpOp::Ne => a != b,
+        }
+    }
+}
+
+/// Operation applied to the register of a statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Inc,
+    Dec,
+    Set,
+    Mul,
+    Div,
+    Mod,
+    Min,
+    Max,
+}
+
+impl Op {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "inc" => Some(Op::Inc),
+            "dec" => Some(Op::Dec),
+            "set" => Some(Op::Set),
+            "mul" => Some(Op::Mul),
+            "div" => Some(Op::Div),
+            "mod" => Some(Op::Mod),
+            "min" => Some(Op::Min),
+            "max" => Some(Op::Max),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operand {
+    Reg(String),
+    Int(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cond {
+    Cmp(Operand, CmpOp, Operand),
+    Not(Box<Cond>),
+    And(Box<Cond>, Box<Cond>),
+    Or(Box<Cond>, Box<Cond>),
+}
+
+/// Statement `register op operand [if cond]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stmt {
+    pub register: String,
+    pub op: Op,
+    pub operand: Operand,
+    pub cond: Option<Cond>,
+    pub span: Span, // span of the statement, without the condition
+}
+
+pub type Program = Vec<Stmt>;
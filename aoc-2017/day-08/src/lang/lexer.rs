@@ -0,0 +1,188 @@
+use super::ast::CmpOp;
+use super::{Error, ErrorKind, Span};
+
+/// Token of the language. The keywords (`if`, `and`, `or`, `not`) and the names
+/// of the operations are identifiers, which the parser tells apart by position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Ident(String),
+    Int(i64),
+    Cmp(CmpOp),
+    LParen,
+    RParen,
+    Newline,
+}
+
+/// Characters which make up the comparison operators.
+fn is_symbol(ch: char) -> bool {
+    matches!(ch, '<' | '>' | '=' | '!')
+}
+
+/// Iterator over the tokens of a source, with their spans.
+pub struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        Self { src, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    /// Advances while the characters satisfy `pred`, and returns the text consumed.
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let start = self.pos;
+
+        while let Some(ch) = self.peek().filter(|&ch| pred(ch)) {
+            self.pos += ch.len_utf8();
+        }
+
+        &self.src[start..self.pos]
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<(Token, Span), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.take_while(|ch| ch != '\n' && ch.is_whitespace());
+
+        let start = self.pos;
+        let ch = self.peek()?;
+
+        let token = match ch {
+            '\n' => {
+                self.pos += 1;
+                Ok(Token::Newline)
+            }
+            '(' => {
+                self.pos += 1;
+                Ok(Token::LParen)
+            }
+            ')' => {
+                self.pos += 1;
+                Ok(Token::RParen)
+            }
+            '-' | '0'..='9' => {
+                self.pos += 1;
+                let digits = self.take_while(|c| c.is_ascii_digit());
+
+                let text = &self.src[start..self.pos];
+
+                // a minus sign must be followed by the digits of an integer
+                match ch == '-' && digits.is_empty() {
+                    true => Err(ErrorKind::UnexpectedChar(ch)),
+                    false => text
+                        .parse()
+                        .map(Token::Int)
+                        .map_err(|_| ErrorKind::InvalidInteger(text.to_string())),
+                }
+            }
+            _ if ch.is_alphabetic() || ch == '_' => {
+                let text = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                Ok(Token::Ident(text.to_string()))
+            }
+            _ if is_symbol(ch) => {
+                // the longest run of symbols is the operator, e.g. `=<` is not `=` then `<`
+                let text = self.take_while(is_symbol);
+
+                CmpOp::from_symbol(text)
+                    .map(Token::Cmp)
+                    .ok_or_else(|| ErrorKind::UnknownOperator(text.to_string()))
+            }
+            _ => {
+                self.pos += ch.len_utf8();
+                Err(ErrorKind::UnexpectedChar(ch))
+            }
+        };
+
+        let span = Span::new(start, self.pos);
+
+        Some(
+            token
+                .map(|token| (token, span))
+                .map_err(|kind| Error::new(kind, span)),
+        )
+    }
+}
+
+pub fn tokenize(src: &str) -> Result<Vec<(Token, Span)>, Error> {
+    Lexer::new(src).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<Token> {
+        tokenize(src)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect()
+    }
+
+    #[test]
+    fn statement() {
+        let expected = [
+            Token::Ident("b".to_string()),
+            Token::Ident("inc".to_string()),
+            Token::Int(-5),
+            Token::Ident("if".to_string()),
+            Token::LParen,
+            Token::Ident("a".to_string()),
+            Token::Cmp(CmpOp::Ge),
+            Token::Int(1),
+            Token::RParen,
+            Token::Newline,
+        ];
+
+        assert_eq!(expected[..], tokens("b inc -5 if (a >= 1)\n"));
+    }
+
+    #[test]
+    fn spans() {
+        let spans: Vec<Span> = tokenize("ab  -12\n")
+            .unwrap()
+            .into_iter()
+            .map(|(_, span)| span)
+            .collect();
+
+        assert_eq!(
+            vec![Span::new(0, 2), Span::new(4, 7), Span::new(7, 8)],
+            spans
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |src| tokenize(src).unwrap_err();
+
+        assert_eq!(
+            Error::new(ErrorKind::UnexpectedChar('-'), Span::new(6, 7)),
+            error("a inc - 1")
+        );
+        assert_eq!(
+            Error::new(
+                ErrorKind::UnknownOperator("=>".to_string()),
+                Span::new(13, 15)
+            ),
+            error("a inc 1 if b => 2")
+        );
+        assert_eq!(
+            Error::new(
+                ErrorKind::InvalidInteger("99999999999999999999".to_string()),
+                Span::new(6, 26)
+            ),
+            error("a inc 99999999999999999999")
+        );
+        assert_eq!(
+            Error::new(ErrorKind::UnexpectedChar('#'), Span::new(2, 3)),
+            error("a # 1")
+        );
+    }
+}
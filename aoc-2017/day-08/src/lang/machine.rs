@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+
+use super::ast::{Cond, Op, Operand, Program, Stmt};
+use super::{Error, ErrorKind};
+
+/// Machine executing the programs, which records the history of every register.
+#[derive(Default)]
+pub struct Machine {
+    registers: HashMap<String, Vec<(usize, i64)>>, // (step, value) of each write
+    step: usize,                                   // number of statements executed
+}
+
+impl Machine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn value(&self, register: &str) -> i64 {
+        self.history(register).last().map_or(0, |&(_, val)| val)
+    }
+
+    /// Returns the writes to the register, as the step of the statement and the value written.
+    pub fn history(&self, register: &str) -> &[(usize, i64)] {
+        self.registers.get(register).map_or(&[], |history| history)
+    }
+
+    fn operand(&self, operand: &Operand) -> i64 {
+        match operand {
+            Operand::Reg(register) => self.value(register),
+            Operand::Int(int) => *int,
+        }
+    }
+
+    pub fn eval(&self, cond: &Cond) -> bool {
+        match cond {
+            Cond::Cmp(a, op, b) => op.apply(self.operand(a), self.operand(b)),
+            Cond::Not(cond) => !self.eval(cond),
+            Cond::And(a, b) => self.eval(a) && self.eval(b),
+            Cond::Or(a, b) => self.eval(a) || self.eval(b),
+        }
+    }
+
+    /// Executes a statement, and returns whether its condition held.
+    pub fn execute(&mut self, stmt: &Stmt) -> Result<bool, Error> {
+        let step = self.step;
+        self.step += 1;
+
+        if !stmt.cond.as_ref().is_none_or(|cond| self.eval(cond)) {
+            return Ok(false);
+        }
+
+        let (a, b) = (self.value(&stmt.register), self.operand(&stmt.operand));
+
+        let value = match stmt.op {
+            Op::Inc => a.checked_add(b),
+            Op::Dec => a.checked_sub(b),
+            Op::Set => Some(b),
+            Op::Mul => a.checked_mul(b),
+            Op::Div | Op::Mod if b == 0 => {
+                return Err(Error::new(ErrorKind::DivisionByZero, stmt.span))
+            }
+            Op::Div => a.checked_div_euclid(b),
+            Op::Mod => a.checked_rem_euclid(b),
+            Op::Min => Some(a.min(b)),
+            Op::Max => Some(a.max(b)),
+        }
+        .ok_or(Error::new(ErrorKind::Overflow, stmt.span))?;
+
+        self.registers
+            .entry(stmt.register.clone())
+            .or_default()
+            .push((step, value));
+
+        Ok(true)
+    }
+
+    pub fn run(&mut self, program: &Program) -> Result<(), Error> {
+        for stmt in program {
+            self.execute(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns a register with the largest current value, amongst the registers written to.
+    pub fn max_current(&self) -> Option<(&str, i64)> {
+        self.registers
+            .keys()
+            .map(|register| (register.as_str(), self.value(register)))
+            .max_by_key(|&(_, val)| val)
+    }
+
+    /// Returns a register with the largest value ever held, amongst the registers written to,
+    /// including their initial value 0.
+    pub fn max_ever(&self) -> Option<(&str, i64)> {
+        self.registers
+            .iter()
+            .map(|(register, history)| {
+                let max = history.iter().map(|&(_, val)| val).max().unwrap_or(0);
+                (register.as_str(), max.max(0))
+            })
+            .max_by_key(|&(_, val)| val)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::parser::parse;
+    use crate::lang::Span;
+
+    fn run(src: &str) -> Result<Machine, Error> {
+        let mut machine = Machine::new();
+        machine.run(&parse(src).unwrap())?;
+
+        Ok(machine)
+    }
+
+    #[test]
+    fn example() {
+        let machine = run("b inc 5 if a > 1\n\
+                           a inc 1 if b < 5\n\
+                           c dec -10 if a >= 1\n\
+                           c inc -20 if c == 10")
+        .unwrap();
+
+        assert_eq!(Some(("a", 1)), machine.max_current());
+        assert_eq!(Some(("c", 10)), machine.max_ever());
+        assert_eq!([(2, 10), (3, -10)], machine.history("c"));
+        assert_eq!(0, machine.value("b"));
+    }
+
+    #[test]
+    fn extended() {
+        // comparisons between registers, nested conditions and new operations
+        let machine = run("a inc 7\n\
+                           b set a if a > 5 and not (a == 6 or a == 8)\n\
+                           b mul 3 if b >= a\n\
+                           c mod b if (c < b or c != 0) and b > 0\n\
+                           a max b\n\
+                           c dec 1 if c <= 0")
+        .unwrap();
+
+        assert_eq!([(0, 7), (4, 21)], machine.history("a"));
+        assert_eq!([(1, 7), (2, 21)], machine.history("b"));
+        assert_eq!([(3, 0), (5, -1)], machine.history("c"));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Error::new(ErrorKind::DivisionByZero, Span::new(8, 15)),
+            run("a inc 1\na div b").err().unwrap()
+        );
+        assert_eq!(
+            Error::new(ErrorKind::Overflow, Span::new(26, 33)),
+            run("a dec 9223372036854775807\na dec 2").err().unwrap()
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use super::ast::{Cond, Op, Operand, Program, Stmt};
+use super::lexer::{tokenize, Token};
+use super::{Error, ErrorKind, Span};
+
+// Grammar of the language, parsed by recursive descent:
+//     program := line ('\n' line)*
+//     line    := (stmt ('if' or)?)?
+//     stmt    := IDENT IDENT operand
+//     or      := and ('or' and)*
+//     and     := not ('and' not)*
+//     not     := 'not' not | '(' or ')' | operand CMP operand
+//     operand := IDENT | INT
+
+struct Parser {
+    tokens: Vec<(Token, Span)>,
+    pos: usize,
+    end: Span, // empty span at the end of the source
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    /// Returns the span of the next token, or the end of the source.
+    fn span(&self) -> Span {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |&(_, span)| span)
+    }
+
+    fn error(&self, expected: &'static str) -> Error {
+        Error::new(ErrorKind::Expected(expected), self.span())
+    }
+
+    /// Consumes the next token if it is the keyword `keyword`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(name)) if name == keyword => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ident(&mut self, expected: &'static str) -> Result<(String, Span), Error> {
+        match self.peek() {
+            Some(Token::Ident(name)) => {
+                let ident = (name.clone(), self.span());
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    fn operand(&mut self) -> Result<(Operand, Span), Error> {
+        let span = self.span();
+
+        let operand = match self.peek() {
+            Some(Token::Ident(name)) => Operand::Reg(name.clone()),
+            Some(&Token::Int(int)) => Operand::Int(int),
+            _ => return Err(self.error("a register or an integer")),
+        };
+
+        self.pos += 1;
+        Ok((operand, span))
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, Error> {
+        let (register, start) = self.ident("a register")?;
+
+        let (name, span) = self.ident("an operation")?;
+        let op = Op::from_name(&name)
+            .ok_or_else(|| Error::new(ErrorKind::UnknownOperator(name), span))?;
+
+        let (operand, end) = self.operand()?;
+
+        let cond = match self.keyword("if") {
+            true => Some(self.or()?),
+            false => None,
+        };
+
+        Ok(Stmt {
+            register,
+            op,
+            operand,
+            cond,
+            span: start.join(end),
+        })
+    }
+
+    fn or(&mut self) -> Result<Cond, Error> {
+        let mut cond = self.and()?;
+
+        while self.keyword("or") {
+            cond = Cond::Or(Box::new(cond), Box::new(self.and()?));
+        }
+
+        Ok(cond)
+    }
+
+    fn and(&mut self) -> Result<Cond, Error> {
+        let mut cond = self.not()?;
+
+        while self.keyword("and") {
+            cond = Cond::And(Box::new(cond), Box::new(self.not()?));
+        }
+
+        Ok(cond)
+    }
+
+    fn not(&mut self) -> Result<Cond, Error> {
+        if self.keyword("not") {
+            return Ok(Cond::Not(Box::new(self.not()?)));
+        }
+
+        if self.peek() == Some(&Token::LParen) {
+            self.pos += 1;
+            let cond = self.or()?;
+
+            if self.peek() != Some(&Token::RParen) {
+                return Err(self.error("')'"));
+            }
+
+            self.pos += 1;
+            return Ok(cond);
+        }
+
+        let (a, _) = self.operand()?;
+
+        let op = match self.peek() {
+            Some(&Token::Cmp(op)) => op,
+            // a word in place of a comparison is an operator we do not know
+            Some(Token::Ident(name)) => {
+                return Err(Error::new(
+                    ErrorKind::UnknownOperator(name.clone()),
+                    self.span(),
+                ))
+            }
+            _ => return Err(self.error("a comparison")),
+        };
+
+        self.pos += 1;
+
+        let (b, _) = self.operand()?;
+
+        Ok(Cond::Cmp(a, op, b))
+    }
+
+    fn program(&mut self) -> Result<Program, Error> {
+        let mut program = Vec::new();
+
+        while self.pos < self.tokens.len() {
+            if self.peek() != Some(&Token::Newline) {
+                program.push(self.stmt()?);
+            }
+
+            match self.peek() {
+                Some(Token::Newline) => self.pos += 1,
+                Some(_) => return Err(self.error("the end of the line")),
+                None => {}
+            }
+        }
+
+        Ok(program)
+    }
+}
+
+pub fn parse(src: &str) -> Result<Program, Error> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        end: Span::new(src.len(), src.len()),
+    };
+
+    parser.program()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::ast::CmpOp;
+
+    fn reg(name: &str) -> Operand {
+        Operand::Reg(name.to_string())
+    }
+
+    #[test]
+    fn statement() {
+        let program = parse("b inc 5 if a > 1\n\na dec -10 if a < 1").unwrap();
+
+        assert_eq!(2, program.len());
+        assert_eq!(
+            Stmt {
+                register: "b".to_string(),
+                op: Op::Inc,
+                operand: Operand::Int(5),
+                cond: Some(Cond::Cmp(reg("a"), CmpOp::Gt, Operand::Int(1))),
+                span: Span::new(0, 7),
+            },
+            program[0]
+        );
+        assert_eq!(Span::new(18, 27), program[1].span);
+    }
+
+    #[test]
+    fn precedence() {
+        // `and` binds tighter than `or`, and `not` tighter than both
+        let program = parse("a set b if not a == 1 or b != c and (c < 0 or c > 9)").unwrap();
+
+        let cmp = |a, op, b| Cond::Cmp(a, op, b);
+        let expected = Cond::Or(
+            Box::new(Cond::Not(Box::new(cmp(
+                reg("a"),
+                CmpOp::Eq,
+                Operand::Int(1),
+            )))),
+            Box::new(Cond::And(
+                Box::new(cmp(reg("b"), CmpOp::Ne, reg("c"))),
+                Box::new(Cond::Or(
+                    Box::new(cmp(reg("c"), CmpOp::Lt, Operand::Int(0))),
+                    Box::new(cmp(reg("c"), CmpOp::Gt, Operand::Int(9))),
+                )),
+            )),
+        );
+
+        assert_eq!(Some(expected), program[0].cond);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |src| parse(src).unwrap_err();
+
+        assert_eq!(
+            Error::new(
+                ErrorKind::UnknownOperator("add".to_string()),
+                Span::new(2, 5)
+            ),
+            error("a add 1")
+        );
+        assert_eq!(
+            Error::new(
+                ErrorKind::UnknownOperator("is".to_string()),
+                Span::new(13, 15)
+            ),
+            error("a inc 1 if b is 2")
+        );
+        assert_eq!(
+            Error::new(ErrorKind::Expected("')'"), Span::new(17, 17)),
+            error("a inc 1 if (b > 2")
+        );
+        assert_eq!(
+            Error::new(ErrorKind::Expected("the end of the line"), Span::new(8, 9)),
+            error("a inc 1 2")
+        );
+        assert_eq!(
+            Error::new(
+                ErrorKind::Expected("a register or an integer"),
+                Span::new(5, 6)
+            ),
+            error("a inc\nb inc 1")
+        );
+    }
+}
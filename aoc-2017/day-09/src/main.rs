@@ -1,46 +1,364 @@
-/// Computes the score of the input and counts the characters within the garbage.
-fn count(stream: &str) -> (u32, u32) {
-    let chars: Vec<_> = stream.chars().collect();
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
 
-    let mut total_score = 0;
-    let mut garbage_char_count = 0;
+/// Garbage inside a group, between `<` and `>`.
+#[derive(Debug)]
+struct Garbage {
+    start: usize,          // byte offset of the `<`
+    end: usize,            // byte offset just after the `>`
+    chars: u32,            // number of characters which are neither cancelled nor cancelling
+    cancelled: Vec<usize>, // byte offsets of the characters cancelled by a `!`
+}
+
+#[derive(Debug)]
+struct Group {
+    start: usize, // byte offset of the `{`
+    end: usize,   // byte offset just after the `}`
+    depth: u32,   // the outermost group has depth 1
+    groups: Vec<Group>,
+    garbage: Vec<Garbage>,
+}
+
+impl Group {
+    fn new(start: usize, depth: u32) -> Self {
+        Self {
+            start,
+            end: start,
+            depth,
+            groups: Vec::new(),
+            garbage: Vec::new(),
+        }
+    }
 
-    let mut current_score = 0;
-    let mut is_garbage = false;
+    /// Returns the group and all the groups within it, outermost first.
+    fn all_groups(&self) -> Vec<&Group> {
+        let mut groups = vec![self];
+        let mut idx = 0;
 
-    let mut idx = 0;
+        while idx < groups.len() {
+            groups.extend(groups[idx].groups.iter());
+            idx += 1;
+        }
+
+        groups
+    }
+
+    /// Computes the total score of the group and of the groups within it.
+    fn score(&self) -> u32 {
+        self.all_groups().iter().map(|group| group.depth).sum()
+    }
 
-    while idx < chars.len() {
-        if is_garbage {
-            match chars[idx] {
-                '!' => idx += 1,
-                '>' => is_garbage = false,
-                _ => garbage_char_count += 1,
+    /// Counts the characters within the garbage of the group and of the groups within it.
+    fn garbage_chars(&self) -> u32 {
+        self.garbage_by_depth().iter().sum()
+    }
+
+    /// Returns the (first) most deeply nested group.
+    fn deepest(&self) -> &Group {
+        self.all_groups()
+            .into_iter()
+            .rev()
+            .max_by_key(|group| group.depth)
+            .unwrap()
+    }
+
+    /// Returns the (first) group with the most groups directly within it.
+    fn largest(&self) -> &Group {
+        self.all_groups()
+            .into_iter()
+            .rev()
+            .max_by_key(|group| group.groups.len())
+            .unwrap()
+    }
+
+    /// Counts the characters within garbage, by depth of the group containing the garbage.
+    /// The count at index 0 is for garbage outside of all groups, so it is always 0.
+    fn garbage_by_depth(&self) -> Vec<u32> {
+        let mut totals = vec![0; self.deepest().depth as usize + 1];
+
+        for group in self.all_groups() {
+            totals[group.depth as usize] += group.garbage.iter().map(|g| g.chars).sum::<u32>();
+        }
+
+        totals
+    }
+}
+
+#[derive(Debug)]
+enum ParseError {
+    Io(io::Error),
+    UnterminatedGroup(usize), // byte offset of the `{` which is never closed
+    UnterminatedGarbage(usize), // byte offset of the `<` which is never closed
+    UnexpectedByte(usize, u8),
+    Empty,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "cannot read the stream: {}", err),
+            ParseError::UnterminatedGroup(offset) => {
+                write!(f, "unterminated group starting at byte {}", offset)
             }
-        } else {
-            match chars[idx] {
-                '<' => is_garbage = true,
-                '{' => current_score += 1,
-                '}' => {
-                    total_score += current_score;
-                    current_score -= 1;
-                }
-                _ => (),
+            ParseError::UnterminatedGarbage(offset) => {
+                write!(f, "unterminated garbage starting at byte {}", offset)
             }
+            ParseError::UnexpectedByte(offset, byte) => {
+                write!(f, "unexpected {:?} at byte {}", *byte as char, offset)
+            }
+            ParseError::Empty => write!(f, "the stream contains no group"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Where the parser is in the stream.
+enum State {
+    Group,
+    Garbage(Garbage),
+    Cancel(Garbage), // the next character is cancelled
+    Done,            // the outermost group is closed, only whitespace may follow
+}
+
+/// Parser building the group tree incrementally, as the bytes of the stream arrive.
+struct Parser {
+    state: State,
+    open: Vec<Group>, // groups which are not closed yet, outermost first
+    root: Option<Group>,
+    offset: usize, // byte offset of the next byte
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            state: State::Group,
+            open: Vec::new(),
+            root: None,
+            offset: 0,
         }
+    }
+
+    fn feed(&mut self, byte: u8) -> Result<(), ParseError> {
+        let offset = self.offset;
+        self.offset += 1;
+
+        let unexpected = Err(ParseError::UnexpectedByte(offset, byte));
+
+        self.state = match std::mem::replace(&mut self.state, State::Group) {
+            State::Group => match byte {
+                b'{' => {
+                    let depth = self.open.len() as u32 + 1;
+                    self.open.push(Group::new(offset, depth));
+                    State::Group
+                }
+                b'}' => {
+                    let Some(mut group) = self.open.pop() else {
+                        return unexpected;
+                    };
 
-        idx += 1;
+                    group.end = offset + 1;
+
+                    match self.open.last_mut() {
+                        Some(parent) => {
+                            parent.groups.push(group);
+                            State::Group
+                        }
+                        None => {
+                            self.root = Some(group);
+                            State::Done
+                        }
+                    }
+                }
+                b'<' if !self.open.is_empty() => State::Garbage(Garbage {
+                    start: offset,
+                    end: offset,
+                    chars: 0,
+                    cancelled: Vec::new(),
+                }),
+                b',' if !self.open.is_empty() => State::Group,
+                _ if byte.is_ascii_whitespace() => State::Group,
+                _ => return unexpected,
+            },
+            State::Garbage(mut garbage) => match byte {
+                b'!' => State::Cancel(garbage),
+                b'>' => {
+                    garbage.end = offset + 1;
+                    self.open.last_mut().unwrap().garbage.push(garbage);
+                    State::Group
+                }
+                _ => {
+                    // count the characters rather than the bytes, by skipping
+                    // the continuation bytes of UTF-8
+                    if byte & 0xC0 != 0x80 {
+                        garbage.chars += 1;
+                    }
+
+                    State::Garbage(garbage)
+                }
+            },
+            State::Cancel(mut garbage) => {
+                garbage.cancelled.push(offset);
+                State::Garbage(garbage)
+            }
+            State::Done if byte.is_ascii_whitespace() => State::Done,
+            State::Done => return unexpected,
+        };
+
+        Ok(())
     }
 
-    (total_score, garbage_char_count)
+    fn finish(self) -> Result<Group, ParseError> {
+        match self.state {
+            State::Garbage(garbage) | State::Cancel(garbage) => {
+                Err(ParseError::UnterminatedGarbage(garbage.start))
+            }
+            _ => match self.open.last() {
+                Some(group) => Err(ParseError::UnterminatedGroup(group.start)),
+                None => self.root.ok_or(ParseError::Empty),
+            },
+        }
+    }
+}
+
+/// Parses a stream made of a single group, reading it by chunks.
+fn parse(mut reader: impl Read) -> Result<Group, ParseError> {
+    let mut parser = Parser::new();
+    let mut buffer = [0; 4096];
+
+    loop {
+        let len = match reader.read(&mut buffer) {
+            Ok(0) => return parser.finish(),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(ParseError::Io(err)),
+        };
+
+        for &byte in &buffer[..len] {
+            parser.feed(byte)?;
+        }
+    }
 }
 
 fn main() {
-    let input = std::fs::read_to_string("input.txt").unwrap();
+    let file = File::open("input.txt").unwrap();
 
-    let (part_one, part_two) = count(&input);
+    let root = parse(file).unwrap_or_else(|err| panic!("{}", err));
+
+    // --- Part One --- //
+
+    let part_one = root.score();
 
     println!("Part One: {}", part_one);
 
+    // --- Part Two --- //
+
+    let part_two = root.garbage_chars();
+
     println!("Part Two: {}", part_two);
+
+    let deepest = root.deepest();
+    let largest = root.largest();
+    let cancelled: usize = root
+        .all_groups()
+        .iter()
+        .flat_map(|group| &group.garbage)
+        .map(|garbage| garbage.cancelled.len())
+        .sum();
+
+    println!(
+        "Deepest group: depth {}, bytes {}..{}",
+        deepest.depth, deepest.start, deepest.end
+    );
+    println!(
+        "Largest group: {} groups directly within, bytes {}..{}",
+        largest.groups.len(),
+        largest.start,
+        largest.end
+    );
+    println!("Cancelled characters: {}", cancelled);
+    println!("Garbage by depth: {:?}", &root.garbage_by_depth()[1..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score() {
+        let examples = [
+            ("{}", 1),
+            ("{{{}}}", 6),
+            ("{{},{}}", 5),
+            ("{{{},{},{{}}}}", 16),
+            ("{<a>,<a>,<a>,<a>}", 1),
+            ("{{<ab>},{<ab>},{<ab>},{<ab>}}", 9),
+            ("{{<!!>},{<!!>},{<!!>},{<!!>}}", 9),
+            ("{{<a!>},{<a!>},{<a!>},{<ab>}}", 3),
+        ];
+
+        for (stream, expected) in examples {
+            assert_eq!(
+                expected,
+                parse(stream.as_bytes()).unwrap().score(),
+                "{}",
+                stream
+            );
+        }
+    }
+
+    #[test]
+    fn garbage() {
+        let examples = [
+            ("<>", 0),
+            ("<random characters>", 17),
+            ("<<<<>", 3),
+            ("<{!>}>", 2),
+            ("<!!>", 0),
+            ("<!!!>>", 0),
+            ("<{o\"i!a,<{i<a>", 10),
+        ];
+
+        for (garbage, expected) in examples {
+            // the garbage must be within a group
+            let root = parse(format!("{{{}}}", garbage).as_bytes()).unwrap();
+
+            assert_eq!(expected, root.garbage_chars(), "{}", garbage);
+        }
+    }
+
+    #[test]
+    fn groups() {
+        let root = parse("{{<a!>>},{{<é>}},{},{}}".as_bytes()).unwrap();
+
+        let depths: Vec<u32> = root.all_groups().iter().map(|group| group.depth).collect();
+        assert_eq!(vec![1, 2, 2, 2, 2, 3], depths);
+
+        let deepest = root.deepest();
+        assert_eq!((3, 10, 16), (deepest.depth, deepest.start, deepest.end));
+
+        assert_eq!(4, root.largest().groups.len());
+        assert_eq!(vec![0, 0, 1, 1], root.garbage_by_depth());
+    }
+
+    #[test]
+    fn errors() {
+        let error = |stream: &str| parse(stream.as_bytes()).unwrap_err();
+
+        assert!(matches!(
+            error("{{<a!>},{}"),
+            ParseError::UnterminatedGarbage(2)
+        ));
+        assert!(matches!(
+            error("{{<ab>},{<!!>}"),
+            ParseError::UnterminatedGroup(0)
+        ));
+        assert!(matches!(
+            error("{{}}}"),
+            ParseError::UnexpectedByte(4, b'}')
+        ));
+        assert!(matches!(error("<a>"), ParseError::UnexpectedByte(0, b'<')));
+        assert!(matches!(error(" \n"), ParseError::Empty));
+    }
 }
@@ -0,0 +1,385 @@
+use std::ops::{Add, Mul, Sub};
+
+// Positions on the hexagonal grid are given in cube coordinates (q, r, s) with q + r + s = 0.
+// Each step in one of the six directions keeps one coordinate and moves the other two
+// by one in opposite ways, so that the distance to the origin is (|q| + |r| + |s|) / 2.
+// The axial coordinates (q, r) are the cube coordinates without the redundant s.
+//
+// The directions are the same for both orientations of the hexagons, only their
+// names and the layout of the hexagons in the plane change.
+
+/// Enum representing all directions on the hexagonal grid.
+///
+/// The directions are named after the flat-topped orientation, and ordered clockwise
+/// starting at `North`, so that the discriminant is the number of turns of pi/3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North = 0,
+    NorthEast = 1,
+    SouthEast = 2,
+    South = 3,
+    SouthWest = 4,
+    NorthWest = 5,
+}
+
+use Direction::*;
+
+pub const DIRECTIONS: [Direction; 6] = [North, NorthEast, SouthEast, South, SouthWest, NorthWest];
+
+impl Direction {
+    /// Returns the vector of a single step in the direction.
+    pub fn vector(&self) -> Hex {
+        match self {
+            North => Hex::new(0, -1),
+            NorthEast => Hex::new(1, -1),
+            SouthEast => Hex::new(1, 0),
+            South => Hex::new(0, 1),
+            SouthWest => Hex::new(-1, 1),
+            NorthWest => Hex::new(-1, 0),
+        }
+    }
+
+    /// Returns the direction after `turns` clockwise turns of pi/3.
+    pub fn rotate(&self, turns: i32) -> Direction {
+        DIRECTIONS[(*self as i32 + turns).rem_euclid(6) as usize]
+    }
+}
+
+/// Orientation of the hexagons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Flat,   // the hexagons have a flat top, and neighbours to the north and south
+    Pointy, // the hexagons have a pointy top, and neighbours to the east and west
+}
+
+impl Orientation {
+    /// Returns the names of the directions, in the order of `DIRECTIONS`.
+    fn names(&self) -> [&'static str; 6] {
+        match self {
+            Orientation::Flat => ["n", "ne", "se", "s", "sw", "nw"],
+            Orientation::Pointy => ["nw", "ne", "e", "se", "sw", "w"],
+        }
+    }
+
+    /// Returns the direction with the name `name` in this orientation.
+    pub fn parse(&self, name: &str) -> Option<Direction> {
+        let idx = self.names().iter().position(|&n| n == name)?;
+        Some(DIRECTIONS[idx])
+    }
+
+    /// Returns the name of the direction in this orientation.
+    pub fn name(&self, dir: Direction) -> &'static str {
+        self.names()[dir as usize]
+    }
+
+    /// Returns the position of the center of the hexagon in the plane,
+    /// for hexagons of radius `size`, with the y-axis pointing down.
+    pub fn to_pixel(&self, hex: Hex, size: f64) -> (f64, f64) {
+        let (q, r) = (hex.q as f64, hex.r as f64);
+        let sqrt3 = 3f64.sqrt();
+
+        match self {
+            Orientation::Flat => (size * 1.5 * q, size * sqrt3 * (r + q / 2.0)),
+            Orientation::Pointy => (size * sqrt3 * (q + r / 2.0), size * 1.5 * r),
+        }
+    }
+}
+
+/// Structure representing a position (or a vector) on the hexagonal grid.
+///
+/// The coordinates are private, so that a position is always built by `Hex::new`
+/// and satisfies q + r + s = 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Hex {
+    q: i32,
+    r: i32,
+    s: i32,
+}
+
+impl Add for Hex {
+    type Output = Hex;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Hex::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for Hex {
+    type Output = Hex;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Hex::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl Mul<i32> for Hex {
+    type Output = Hex;
+
+    fn mul(self, rhs: i32) -> Self::Output {
+        Hex::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+impl Hex {
+    /// Creates a position from its axial coordinates.
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r, s: -q - r }
+    }
+
+    pub fn axial(&self) -> (i32, i32) {
+        (self.q, self.r)
+    }
+
+    pub fn cube(&self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s)
+    }
+
+    /// Computes the norm of the position.
+    ///
+    /// The norm is the minimal number of steps necessary to reach the position.
+    pub fn length(&self) -> u32 {
+        (self.q.unsigned_abs() + self.r.unsigned_abs() + self.s.unsigned_abs()) / 2
+    }
+
+    pub fn distance(&self, other: Hex) -> u32 {
+        (*self - other).length()
+    }
+
+    pub fn neighbour(&self, dir: Direction) -> Hex {
+        *self + dir.vector()
+    }
+
+    pub fn neighbours(&self) -> [Hex; 6] {
+        DIRECTIONS.map(|dir| self.neighbour(dir))
+    }
+
+    /// Returns the positions at distance `radius`, clockwise starting from the north.
+    pub fn ring(&self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![*self];
+        }
+
+        let mut hex = *self + North.vector() * radius as i32;
+        let mut ring = Vec::with_capacity(6 * radius as usize);
+
+        // walk along each side, turning by 2pi/3 from the direction of the corner
+        for dir in DIRECTIONS {
+            for _ in 0..radius {
+                ring.push(hex);
+                hex = hex.neighbour(dir.rotate(2));
+            }
+        }
+
+        ring
+    }
+
+    /// Rounds fractional cube coordinates to the nearest position.
+    fn round(q: f64, r: f64, s: f64) -> Hex {
+        let (rq, rr, rs) = (q.round(), r.round(), s.round());
+        let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+        // fix the coordinate with the largest rounding error so that q + r + s = 0
+        if dq > dr && dq > ds {
+            Hex::new((-rr - rs) as i32, rr as i32)
+        } else if dr > ds {
+            Hex::new(rq as i32, (-rq - rs) as i32)
+        } else {
+            Hex::new(rq as i32, rr as i32)
+        }
+    }
+
+    /// Returns the positions on the straight line from `self` to `other`, both included.
+    pub fn line(&self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+
+        // nudge the endpoints so that the points on an edge are rounded consistently
+        let eps = 1e-6;
+        let (a, b) = (
+            (
+                self.q as f64 + eps,
+                self.r as f64 + eps,
+                self.s as f64 - 2.0 * eps,
+            ),
+            (
+                other.q as f64 + eps,
+                other.r as f64 + eps,
+                other.s as f64 - 2.0 * eps,
+            ),
+        );
+
+        (0..=n)
+            .map(|i| {
+                let t = if n == 0 { 0.0 } else { i as f64 / n as f64 };
+                let lerp = |x: f64, y: f64| x + (y - x) * t;
+
+                Hex::round(lerp(a.0, b.0), lerp(a.1, b.1), lerp(a.2, b.2))
+            })
+            .collect()
+    }
+}
+
+/// Returns a shortest path with the same endpoints as `path`.
+///
+/// The path uses at most two directions, which are adjacent on the circle.
+pub fn simplify(path: &[Direction]) -> Vec<Direction> {
+    let mut target = path
+        .iter()
+        .fold(Hex::default(), |hex, dir| hex + dir.vector());
+
+    let mut simple = Vec::with_capacity(target.length() as usize);
+
+    // every step towards the target reduces the distance by one
+    while target != Hex::default() {
+        let dir = DIRECTIONS
+            .into_iter()
+            .find(|dir| (target - dir.vector()).length() < target.length())
+            .unwrap();
+
+        target = target - dir.vector();
+        simple.push(dir);
+    }
+
+    simple
+}
+
+/// Follows the route from `start` and returns the end position,
+/// along with the furthest distance from `start` reached on the way.
+pub fn walk(start: Hex, route: impl IntoIterator<Item = Direction>) -> (Hex, u32) {
+    route.into_iter().fold((start, 0), |(hex, furthest), dir| {
+        let hex = hex.neighbour(dir);
+        (hex, furthest.max(hex.distance(start)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(names: &str) -> Vec<Direction> {
+        names
+            .split(',')
+            .map(|name| Orientation::Flat.parse(name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn distances() {
+        let examples = [
+            ("ne,ne,ne", 3),
+            ("ne,ne,sw,sw", 0),
+            ("ne,ne,s,s", 2),
+            ("se,sw,se,sw,sw", 3),
+        ];
+
+        for (names, expected) in examples {
+            let (end, _) = walk(Hex::default(), route(names));
+            assert_eq!(expected, end.length(), "{}", names);
+        }
+    }
+
+    #[test]
+    fn furthest() {
+        let (end, furthest) = walk(Hex::default(), route("ne,ne,sw,sw"));
+
+        assert_eq!((Hex::default(), 2), (end, furthest));
+    }
+
+    #[test]
+    fn simplify_length() {
+        for names in [
+            "ne,ne,ne",
+            "ne,ne,sw,sw",
+            "ne,ne,s,s",
+            "se,sw,se,sw,sw",
+            "n,se,sw,nw,n",
+        ] {
+            let route = route(names);
+            let (end, _) = walk(Hex::default(), route.iter().copied());
+
+            // the simplified path is a shortest path to the same position
+            let path = simplify(&route);
+            assert_eq!(end.length() as usize, path.len(), "{}", names);
+            assert_eq!((end, end.length()), walk(Hex::default(), path));
+        }
+    }
+
+    #[test]
+    fn ring() {
+        let center = Hex::new(2, -1);
+
+        assert_eq!(vec![center], center.ring(0));
+        assert_eq!(center.neighbours().to_vec(), center.ring(1));
+
+        let ring = center.ring(2);
+        assert_eq!(12, ring.len());
+        assert!(ring.iter().all(|hex| hex.distance(center) == 2));
+        assert_eq!(center + North.vector() * 2, ring[0]);
+    }
+
+    #[test]
+    fn line() {
+        let origin = Hex::default();
+
+        assert_eq!(vec![origin], origin.line(origin));
+
+        // along a direction, the line is the walk in that direction
+        let end = NorthEast.vector() * 3;
+        let walk: Vec<Hex> = (0..=3).map(|i| NorthEast.vector() * i).collect();
+        assert_eq!(walk, origin.line(end));
+
+        // the midpoint on an edge is rounded towards the nudged side
+        assert_eq!(
+            vec![origin, Hex::new(1, 0), Hex::new(2, -1)],
+            origin.line(Hex::new(2, -1))
+        );
+
+        // every line is a shortest path between its endpoints
+        for end in Hex::new(1, -2).ring(4) {
+            let line = Hex::new(1, -2).line(end);
+
+            assert_eq!(5, line.len());
+            assert_eq!((Hex::new(1, -2), end), (line[0], line[4]));
+            assert!(line.windows(2).all(|w| w[0].distance(w[1]) == 1));
+        }
+    }
+
+    #[test]
+    fn cube() {
+        let hex = Hex::new(2, -5);
+
+        assert_eq!((2, -5, 3), hex.cube());
+        assert_eq!((2, -5), hex.axial());
+
+        let (q, r, s) = (hex - South.vector() * 7).cube();
+        assert_eq!(0, q + r + s);
+    }
+
+    #[test]
+    fn to_pixel() {
+        let sqrt3 = 3f64.sqrt();
+        let close = |(x, y): (f64, f64), (ex, ey): (f64, f64)| {
+            (x - ex).abs() < 1e-9 && (y - ey).abs() < 1e-9
+        };
+
+        let flat = |hex| Orientation::Flat.to_pixel(hex, 2.0);
+        assert!(close(flat(Hex::default()), (0.0, 0.0)));
+        assert!(close(flat(North.vector()), (0.0, -2.0 * sqrt3)));
+        assert!(close(flat(SouthEast.vector()), (3.0, sqrt3)));
+
+        let pointy = |hex| Orientation::Pointy.to_pixel(hex, 2.0);
+        assert!(close(pointy(SouthEast.vector()), (2.0 * sqrt3, 0.0)));
+        assert!(close(pointy(South.vector()), (sqrt3, 3.0)));
+    }
+
+    #[test]
+    fn names() {
+        for orientation in [Orientation::Flat, Orientation::Pointy] {
+            for dir in DIRECTIONS {
+                assert_eq!(Some(dir), orientation.parse(orientation.name(dir)));
+            }
+        }
+
+        assert_eq!(Some(SouthEast), Orientation::Pointy.parse("e"));
+        assert_eq!(None, Orientation::Flat.parse("e"));
+    }
+}
@@ -1,20 +1,195 @@
-use std::collections::HashSet;
-
-// The knot_hash module contains the Knot Hash struct from day-10.
-pub mod knot_hash;
-
-use knot_hash::KnotHash;
-
-/// Converts a string of hexadecimal digits to a string of binary digits.
-///
-/// Each hex digit is represented as four bits.
-fn hex_to_binary(hex_str: &str) -> String {
-    hex_str
-        .chars()
-        .map(|ch| ch.to_digit(16).unwrap())
-        .map(|d| format!("{d:04b}"))
-        .collect::<Vec<_>>()
-        .concat()
+use std::fmt::Write;
+
+const SIZE: usize = 128;
+
+/// Grid of the disk, where each row is the knot hash of its key.
+/// The bit of the square in column `col` is the bit `127 - col` of the row.
+struct Disk {
+    rows: [u128; SIZE],
+}
+
+impl Disk {
+    /// Builds the grid from the key string, hashing the rows in parallel.
+    fn new(key: &str) -> Self {
+        let keys: Vec<String> = (0..SIZE).map(|row| format!("{key}-{row}")).collect();
+        let digests = knot_hash::hash_batch(&keys);
+
+        Self {
+            rows: core::array::from_fn(|row| digests[row].to_u128()),
+        }
+    }
+
+    fn is_used(&self, row: usize, col: usize) -> bool {
+        self.rows[row] >> (SIZE - 1 - col) & 1 == 1
+    }
+
+    /// Counts the used squares.
+    fn used(&self) -> u32 {
+        self.rows.iter().map(|row| row.count_ones()).sum()
+    }
+
+    /// Labels the regions of adjacent used squares, by merging each used square
+    /// with its used neighbours to the right and below.
+    fn regions(&self) -> Regions {
+        let mut sets = UnionFind::new(SIZE * SIZE);
+
+        for row in 0..SIZE {
+            // squares used both in the column and in the next column (resp. the next row)
+            let right = self.rows[row] & self.rows[row] << 1;
+            let below = match row + 1 < SIZE {
+                true => self.rows[row] & self.rows[row + 1],
+                false => 0,
+            };
+
+            for col in 0..SIZE {
+                let bit = SIZE - 1 - col;
+
+                if right >> bit & 1 == 1 {
+                    sets.union(row * SIZE + col, row * SIZE + col + 1);
+                }
+
+                if below >> bit & 1 == 1 {
+                    sets.union(row * SIZE + col, (row + 1) * SIZE + col);
+                }
+            }
+        }
+
+        // number the regions in the order of their first square
+        let mut ids = vec![None; SIZE * SIZE];
+        let mut labels = vec![None; SIZE * SIZE];
+        let mut count = 0;
+
+        for row in 0..SIZE {
+            for col in (0..SIZE).filter(|&col| self.is_used(row, col)) {
+                let root = sets.find(row * SIZE + col);
+
+                let id = *ids[root].get_or_insert_with(|| {
+                    count += 1;
+                    count - 1
+                });
+
+                labels[row * SIZE + col] = Some(id);
+            }
+        }
+
+        Regions { labels, count }
+    }
+}
+
+/// Disjoint sets of squares, with union by rank and path halving.
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            rank: vec![0; len],
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parent[idx] != idx {
+            self.parent[idx] = self.parent[self.parent[idx]];
+            idx = self.parent[idx];
+        }
+
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+
+        if a == b {
+            return;
+        }
+
+        let (low, high) = match self.rank[a] < self.rank[b] {
+            true => (a, b),
+            false => (b, a),
+        };
+
+        self.parent[low] = high;
+
+        if self.rank[low] == self.rank[high] {
+            self.rank[high] += 1;
+        }
+    }
+}
+
+/// Region of each square of the disk.
+struct Regions {
+    labels: Vec<Option<u32>>, // region id of each square, `None` for free squares
+    count: u32,
+}
+
+impl Regions {
+    fn label(&self, row: usize, col: usize) -> Option<u32> {
+        self.labels[row * SIZE + col]
+    }
+
+    /// Draws the top left corner of the disk, with a symbol per region
+    /// (reused every 62 regions) and `.` for the free squares.
+    fn render_ascii(&self, rows: usize, cols: usize) -> String {
+        let symbols: Vec<char> = ('0'..='9').chain('a'..='z').chain('A'..='Z').collect();
+
+        let mut drawing = String::new();
+
+        for row in 0..rows {
+            for col in 0..cols {
+                drawing.push(match self.label(row, col) {
+                    Some(id) => symbols[id as usize % symbols.len()],
+                    None => '.',
+                });
+            }
+
+            drawing.push('\n');
+        }
+
+        drawing
+    }
+
+    /// Draws the disk as a plain PPM image, with `scale` pixels per square,
+    /// a colour per region and black for the free squares.
+    fn render_ppm(&self, scale: usize) -> String {
+        let side = SIZE * scale;
+        let mut image = format!("P3\n{side} {side}\n255\n");
+
+        for row in 0..side {
+            for col in 0..side {
+                let (r, g, b) = match self.label(row / scale, col / scale) {
+                    Some(id) => colour(id),
+                    None => (0, 0, 0),
+                };
+
+                let _ = writeln!(image, "{r} {g} {b}");
+            }
+        }
+
+        image
+    }
+}
+
+/// Returns a bright colour for a region, with hues spread by the golden ratio
+/// so that the regions next to each other (with close ids) have distinct colours.
+fn colour(id: u32) -> (u8, u8, u8) {
+    let hue = (id as f64 * 0.618_033_988_75).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+
+    let byte = |c: f64| (55.0 + 200.0 * c) as u8;
+
+    (byte(r), byte(g), byte(b))
 }
 
 fn main() {
@@ -23,63 +198,122 @@ fn main() {
         .trim()
         .to_string();
 
-    // build a set to contain the coordinates of the used squares (as (row, col)).
-    let mut set: HashSet<(i32, i32)> = HashSet::new();
-
-    // fill the set by hashing the input
-    for row in 0..128 {
-        // compute the KnotHash digest and convert it to a binary string
-        let hex_digest = KnotHash::hash(&format!("{input}-{row}"));
-        let bin_digest = hex_to_binary(&hex_digest);
-
-        // filters the pairs (row, col) where the value is 1
-        let pairs = bin_digest
-            .chars()
-            .enumerate()
-            .filter_map(|(col, ch)| match ch {
-                '1' => Some((row, col as i32)),
-                _ => None,
-            });
-
-        set.extend(pairs);
-    }
+    let disk = Disk::new(&input);
 
     // --- Part One --- //
 
-    let part_one = set.len();
+    let part_one = disk.used();
 
     println!("Part One: {}", part_one);
 
     // --- Part Two --- //
 
-    let mut component_count = 0;
+    let regions = disk.regions();
 
-    // find an element in the set that has not yet been visited
-    while let Some(&elt) = set.iter().next() {
-        // remove all elements connected to it from the set
+    let part_two = regions.count;
 
-        let mut stack = Vec::from([elt]);
+    println!("Part Two: {}", part_two);
 
-        while let Some(current) = stack.pop() {
-            let neighbors = [
-                (current.0 - 1, current.1),
-                (current.0 + 1, current.1),
-                (current.0, current.1 - 1),
-                (current.0, current.1 + 1),
-            ]
-            .into_iter()
-            .filter(|nbh| set.contains(nbh));
+    println!("{}", regions.render_ascii(8, 8));
 
-            stack.extend(neighbors);
+    // the image is written to the path given as argument, if any
+    if let Some(path) = std::env::args().nth(1) {
+        std::fs::write(path, regions.render_ppm(4)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a disk from the used squares, given as (row, col).
+    fn disk(used: &[(usize, usize)]) -> Disk {
+        let mut rows = [0; SIZE];
 
-            set.remove(&current);
+        for &(row, col) in used {
+            rows[row] |= 1 << (SIZE - 1 - col);
         }
 
-        // the component containing the element has been removed from the set
-        component_count += 1;
+        Disk { rows }
     }
 
-    let part_two = component_count;
+    #[test]
+    fn example() {
+        let disk = Disk::new("flqrgnkx");
 
-    println!("Part Two: {}", part_two);
+        assert_eq!(8108, disk.used());
+
+        let regions = disk.regions();
+        assert_eq!(1242, regions.count);
+
+        // the corner shown in the puzzle, with the regions drawn as `#`
+        let corner: String = regions
+            .render_ascii(8, 8)
+            .chars()
+            .map(|c| match c {
+                '.' | '\n' => c,
+                _ => '#',
+            })
+            .collect();
+
+        let expected = "\
+##.#.#..
+.#.#.#.#
+....#.#.
+#.#.##.#
+.##.#...
+##..#..#
+.#...#..
+##.#.##.
+";
+        assert_eq!(expected, corner);
+    }
+
+    #[test]
+    fn union_find() {
+        let mut sets = UnionFind::new(6);
+
+        sets.union(0, 1);
+        sets.union(2, 3);
+        assert_ne!(sets.find(0), sets.find(2));
+
+        sets.union(1, 3);
+        sets.union(0, 2); // already merged
+        assert_eq!(sets.find(0), sets.find(3));
+        assert_eq!(sets.find(1), sets.find(2));
+
+        assert_ne!(sets.find(0), sets.find(4));
+        assert_ne!(sets.find(4), sets.find(5));
+        assert_eq!(5, sets.find(5));
+    }
+
+    #[test]
+    fn render_ascii() {
+        let regions = disk(&[(0, 0), (0, 1), (1, 3), (2, 3), (2, 0)]).regions();
+
+        assert_eq!(3, regions.count);
+        assert_eq!("00..\n...1\n2..1\n", regions.render_ascii(3, 4));
+    }
+
+    #[test]
+    fn render_ppm() {
+        let regions = disk(&[(0, 0), (0, 1), (1, 0)]).regions();
+        let image = regions.render_ppm(2);
+
+        let mut lines = image.lines();
+        assert_eq!(Some("P3"), lines.next());
+        assert_eq!(Some("256 256"), lines.next());
+        assert_eq!(Some("255"), lines.next());
+
+        let pixels: Vec<&str> = lines.collect();
+        assert_eq!(256 * 256, pixels.len());
+
+        let pixel = |row: usize, col: usize| pixels[row * 256 + col];
+
+        assert_eq!("255 55 55", pixel(0, 0)); // colour of the first region
+        assert_eq!(pixel(0, 0), pixel(1, 3)); // square (0, 1)
+        assert_eq!(pixel(0, 0), pixel(3, 1)); // square (1, 0)
+        assert_eq!("0 0 0", pixel(0, 4)); // square (0, 2)
+        assert_eq!("0 0 0", pixel(3, 3)); // square (1, 1)
+    }
 }
@@ -1,3 +1,5 @@
+use std::thread;
+
 fn parse_input(input: &str) -> (u64, u64) {
     let nums: Vec<_> = input
         .lines()
@@ -11,17 +13,45 @@ const RATIO_A: u64 = 16807;
 const RATIO_B: u64 = 48271;
 const MODULUS: u64 = 2147483647;
 
-const BITS: u64 = 65535; // = 2^16 - 1 = 0x11...1 (16 1s)
+const LOW_16: u64 = 0xFFFF; // the 16 lowest bits
+
+// Number of values generated by each thread in a single go.
+const CHUNK: u64 = 1 << 20;
+
+/// Computes `base^exp % modulus` by squaring.
+/// The modulus is below 2^32, so the products fit in 64 bits.
+fn pow_mod(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1;
+    base %= modulus;
+
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+
+    result
+}
 
-struct Judge;
+struct Judge {
+    mask: u64, // the bits to compare
+}
 
 impl Judge {
-    /// Compares the last (lowest) 16 bits of two numbers.
-    fn compare(x: u64, y: u64) -> bool {
-        (x & BITS) == (y & BITS)
+    fn new(mask: u64) -> Self {
+        Self { mask }
+    }
+
+    /// Compares the bits of the mask of two numbers.
+    fn compare(&self, x: u64, y: u64) -> bool {
+        (x ^ y) & self.mask == 0
     }
 }
 
+#[derive(Clone)]
 struct Generator {
     value: u64,
     ratio: u64,
@@ -42,6 +72,31 @@ impl Generator {
     fn update_value(&mut self) {
         self.value = (self.value * self.ratio) % self.modulus;
     }
+
+    /// Skips the next `n` values of the sequence, whether they satisfy the condition or not.
+    ///
+    /// The value after `n` steps is `value * ratio^n`, so the jump takes O(log n) time.
+    /// Unlike `Iterator::skip`, the values are not generated one by one.
+    fn jump(mut self, n: u64) -> Self {
+        self.value = self.value * pow_mod(self.ratio, n, self.modulus) % self.modulus;
+        self
+    }
+
+    /// Returns the values among the next `n` values of the sequence which satisfy
+    /// the condition, masked with `mask`.
+    fn scan(mut self, n: u64, mask: u64) -> Vec<u64> {
+        let mut values = Vec::new();
+
+        for _ in 0..n {
+            self.update_value();
+
+            if (self.condition)(self.value) {
+                values.push(self.value & mask);
+            }
+        }
+
+        values
+    }
 }
 
 impl Iterator for Generator {
@@ -58,44 +113,172 @@ impl Iterator for Generator {
     }
 }
 
+fn threads() -> u64 {
+    thread::available_parallelism().map_or(1, |n| n.get() as u64)
+}
+
+/// Counts the matching pairs among the first `count` values of two generators
+/// which accept all values. Each thread skips directly to its own chunk of pairs.
+fn count_matches(a: &Generator, b: &Generator, count: u64, judge: &Judge) -> usize {
+    let chunk = count.div_ceil(threads()).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..count)
+            .step_by(chunk as usize)
+            .map(|start| {
+                let len = chunk.min(count - start) as usize;
+                let (a, b) = (a.clone().jump(start), b.clone().jump(start));
+
+                scope.spawn(move || {
+                    a.zip(b)
+                        .take(len)
+                        .filter(|&(val_a, val_b)| judge.compare(val_a, val_b))
+                        .count()
+                })
+            })
+            .collect();
+
+        handles.into_iter().map(|h| h.join().unwrap()).sum()
+    })
+}
+
+/// Returns the first `count` values accepted by a picky generator, masked with `mask`.
+///
+/// The values accepted in a chunk of the sequence depend on the chunk only, so the threads
+/// scan consecutive chunks of the sequence, which are put back together in order.
+fn picky_values(generator: &Generator, count: usize, mask: u64) -> Vec<u64> {
+    let mut values = Vec::with_capacity(count);
+    let mut start = 0;
+
+    while values.len() < count {
+        let chunks: Vec<Vec<u64>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads())
+                .map(|idx| {
+                    let generator = generator.clone().jump(start + idx * CHUNK);
+                    scope.spawn(move || generator.scan(CHUNK, mask))
+                })
+                .collect();
+
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        start += threads() * CHUNK;
+        values.extend(chunks.into_iter().flatten());
+    }
+
+    values.truncate(count);
+    values
+}
+
 fn main() {
     let input = std::fs::read_to_string("input.txt").unwrap();
 
     let (input_a, input_b) = parse_input(&input);
 
+    let judge = Judge::new(LOW_16);
+
     // --- Part One --- //
 
-    let mut generator_a = Generator::new(input_a, RATIO_A, |_| true);
-    let mut generator_b = Generator::new(input_b, RATIO_B, |_| true);
+    let all_a = Generator::new(input_a, RATIO_A, |_| true);
+    let all_b = Generator::new(input_b, RATIO_B, |_| true);
 
-    let mut part_one = 0;
+    let part_one = count_matches(&all_a, &all_b, 40_000_000, &judge);
 
-    for _ in 0..40_000_000 {
-        let val_a = generator_a.next().unwrap();
-        let val_b = generator_b.next().unwrap();
+    println!("Part One: {}", part_one);
 
-        if Judge::compare(val_a, val_b) {
-            part_one += 1;
-        }
+    // --- Part Two --- //
+
+    let generator_a = Generator::new(input_a, RATIO_A, |val| val % 4 == 0);
+    let generator_b = Generator::new(input_b, RATIO_B, |val| val % 8 == 0);
+
+    let values_a = picky_values(&generator_a, 5_000_000, judge.mask);
+    let values_b = picky_values(&generator_b, 5_000_000, judge.mask);
+
+    let part_two = values_a
+        .iter()
+        .zip(&values_b)
+        .filter(|&(&val_a, &val_b)| judge.compare(val_a, val_b))
+        .count();
+
+    println!("Part Two: {}", part_two);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn example() -> (Generator, Generator) {
+        (
+            Generator::new(65, RATIO_A, |_| true),
+            Generator::new(8921, RATIO_B, |_| true),
+        )
     }
 
-    println!("Part One: {}", part_one);
+    #[test]
+    fn values() {
+        let (a, b) = example();
 
-    // --- Part Two --- //
+        let expected_a = [1092455, 1181022009, 245556042, 1744312007, 1352636452];
+        let expected_b = [430625591, 1233683848, 1431495498, 137874439, 285222916];
 
-    let mut generator_a = Generator::new(input_a, RATIO_A, |val| val % 4 == 0);
-    let mut generator_b = Generator::new(input_b, RATIO_B, |val| val % 8 == 0);
+        assert_eq!(expected_a[..], a.take(5).collect::<Vec<_>>());
+        assert_eq!(expected_b[..], b.take(5).collect::<Vec<_>>());
+    }
 
-    let mut part_two = 0;
+    #[test]
+    fn jump() {
+        let (a, _) = example();
 
-    for _ in 0..5_000_000 {
-        let val_a = generator_a.next().unwrap();
-        let val_b = generator_b.next().unwrap();
+        // jumping gives the same values as generating them
+        assert_eq!(a.clone().nth(1000), a.clone().jump(1000).next());
+        assert_eq!(a.clone().next(), a.clone().jump(0).next());
+    }
 
-        if Judge::compare(val_a, val_b) {
-            part_two += 1;
-        }
+    #[test]
+    fn part_one() {
+        let (a, b) = example();
+
+        assert_eq!(1, count_matches(&a, &b, 5, &Judge::new(LOW_16)));
+        assert_eq!(588, count_matches(&a, &b, 40_000_000, &Judge::new(LOW_16)));
     }
 
-    println!("Part Two: {}", part_two);
+    #[test]
+    fn part_two() {
+        let a = Generator::new(65, RATIO_A, |val| val % 4 == 0);
+        let b = Generator::new(8921, RATIO_B, |val| val % 8 == 0);
+
+        let values_a = picky_values(&a, 5_000_000, LOW_16);
+        let values_b = picky_values(&b, 5_000_000, LOW_16);
+
+        assert_eq!(
+            a.clone()
+                .take(5)
+                .map(|val| val & LOW_16)
+                .collect::<Vec<_>>(),
+            values_a[..5]
+        );
+
+        let matches: Vec<usize> = (0..values_a.len())
+            .filter(|&idx| values_a[idx] == values_b[idx])
+            .collect();
+
+        // the first match is the 1056th pair
+        assert_eq!(1055, matches[0]);
+        assert_eq!(309, matches.len());
+    }
+
+    #[test]
+    fn judge_8_bits() {
+        let (a, b) = example();
+        let judge = Judge::new(0xFF);
+
+        let expected = a
+            .clone()
+            .zip(b.clone())
+            .take(100_000)
+            .filter(|&(val_a, val_b)| (val_a ^ val_b) % 256 == 0)
+            .count();
+
+        assert_eq!(expected, count_matches(&a, &b, 100_000, &judge));
+    }
 }
@@ -1,3 +1,9 @@
+use std::collections::HashMap;
+
+const PROGRAMS: usize = 16;
+const ROUNDS: u64 = 1_000_000_000;
+const MAX_PROGRAMS: usize = 26; // the programs are named by the letters a to z
+
 enum Move {
     Spin(usize),
     Exchange(usize, usize),
@@ -32,98 +38,215 @@ impl From<&str> for Move {
     }
 }
 
-struct Dance {
-    values: [char; 16],
+impl Move {
+    /// Checks that the move only refers to positions and programs of a line of `programs`.
+    fn check(&self, programs: usize) {
+        match *self {
+            Move::Spin(_) => {}
+            Move::Exchange(x, y) => assert!(
+                x < programs && y < programs,
+                "exchange x{}/{} is out of the line of {} programs",
+                x,
+                y,
+                programs
+            ),
+            Move::Partner(x, y) => {
+                index(x, programs);
+                index(y, programs);
+            }
+        }
+    }
 }
 
-impl Dance {
-    fn new() -> Self {
-        let programs = [
-            'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p',
-        ];
+/// Checks the number of programs of a line, which are named after its first letters.
+fn check_programs(programs: usize) {
+    assert!(programs > 0, "the dance needs at least one program");
+    assert!(
+        programs <= MAX_PROGRAMS,
+        "the dance has at most {} programs",
+        MAX_PROGRAMS
+    );
+}
 
-        Self { values: programs }
+/// Returns the index of a program from its name (`a` is 0).
+fn index(name: char, programs: usize) -> usize {
+    let idx = (name as usize).wrapping_sub('a' as usize);
+    assert!(idx < programs, "program {} is not in the dance", name);
+    idx
+}
+
+fn name(idx: usize) -> char {
+    (b'a' + idx as u8) as char
+}
+
+// A dance permutes the line in two independent ways. Spins and exchanges move the programs
+// according to their positions, whatever their names, while partners swap the programs
+// according to their names, wherever they are. So a dance is a pair of permutations: one
+// of the positions and one of the names. The two kinds of moves commute, and the moves of
+// each kind compose into a single permutation, so the dance repeated n times is the pair
+// of permutations to the power n, which takes O(log n) compositions.
+
+/// Permutation of 0..n, as the image of each element.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Permutation(Vec<usize>);
+
+impl Permutation {
+    fn identity(len: usize) -> Self {
+        Self((0..len).collect())
+    }
+
+    /// Returns the permutation `x -> self(other(x))`.
+    fn compose(&self, other: &Permutation) -> Permutation {
+        Permutation(other.0.iter().map(|&x| self.0[x]).collect())
     }
+}
 
-    /// Applies a single move.
-    fn apply_move(&mut self, mv: &Move) {
-        match mv {
-            Move::Spin(x) => self.spin(*x),
-            Move::Exchange(x, y) => self.exchange(*x, *y),
-            Move::Partner(x, y) => self.partner(*x, *y),
+/// Dance as a pair of permutations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Dance {
+    positions: Permutation, // the program at position i comes from position positions(i)
+    names: Permutation,     // the program named x is now named names(x)
+}
+
+impl Dance {
+    fn identity(programs: usize) -> Self {
+        check_programs(programs);
+
+        Self {
+            positions: Permutation::identity(programs),
+            names: Permutation::identity(programs),
         }
     }
 
-    /// Applies a sequence of moves.
-    fn apply_moves(&mut self, moves: &[Move]) {
+    /// Compiles a sequence of moves into a dance of `programs` programs.
+    fn new(moves: &[Move], programs: usize) -> Self {
+        let mut dance = Self::identity(programs);
+
         for mv in moves {
-            self.apply_move(mv);
+            mv.check(programs);
+
+            match *mv {
+                Move::Spin(x) => dance.positions.0.rotate_right(x % programs),
+                Move::Exchange(x, y) => dance.positions.0.swap(x, y),
+                Move::Partner(x, y) => {
+                    let (x, y) = (index(x, programs), index(y, programs));
+
+                    // rename x to y and y to x, after the previous renamings
+                    for name in dance.names.0.iter_mut() {
+                        if *name == x {
+                            *name = y;
+                        } else if *name == y {
+                            *name = x;
+                        }
+                    }
+                }
+            }
         }
+
+        dance
     }
 
-    /// Finds the period of a sequence of moves.
-    ///
-    /// We define the period as the smallest positive number of times
-    /// that the sequence needs to be repeated before returning to the initial state.
-    fn period(moves: &[Move]) -> usize {
-        let mut dance = Self::new();
-        let initial_values = dance.values;
+    /// Returns the dance `self` followed by the dance `other`.
+    fn then(&self, other: &Dance) -> Dance {
+        Dance {
+            positions: self.positions.compose(&other.positions),
+            names: other.names.compose(&self.names),
+        }
+    }
 
-        dance.apply_moves(moves);
+    /// Returns the dance repeated `times` times, by squaring.
+    fn pow(&self, mut times: u64) -> Dance {
+        let mut result = Dance::identity(self.positions.0.len());
+        let mut base = self.clone();
 
-        let mut count = 1;
+        while times > 0 {
+            if times & 1 == 1 {
+                result = result.then(&base);
+            }
 
-        while dance.values != initial_values {
-            dance.apply_moves(moves);
-            count += 1;
+            base = base.then(&base);
+            times >>= 1;
         }
 
-        count
+        result
     }
 
-    /// Applies a Spin move.
-    fn spin(&mut self, val: usize) {
-        let split_idx = self.values.len() - val; // first index of tail
+    /// Returns the line after the dance, starting from the programs in order.
+    fn line(&self) -> String {
+        self.positions
+            .0
+            .iter()
+            .map(|&from| name(self.names.0[from]))
+            .collect()
+    }
+}
 
-        let (head, tail) = self.values.split_at(split_idx);
+/// Line of programs, where the moves are applied one at a time.
+struct Line {
+    values: Vec<char>,
+}
 
-        let head = head.to_owned();
-        let tail = tail.to_owned();
+impl Line {
+    fn new(programs: usize) -> Self {
+        check_programs(programs);
 
-        self.values[..tail.len()].copy_from_slice(&tail);
-        self.values[tail.len()..].copy_from_slice(&head);
+        Self {
+            values: (0..programs).map(name).collect(),
+        }
     }
 
-    /// Applies an Exchange move.
-    fn exchange(&mut self, first: usize, second: usize) {
-        (self.values[first], self.values[second]) = (self.values[second], self.values[first]);
-    }
+    /// Applies a sequence of moves.
+    fn apply_moves(&mut self, moves: &[Move]) {
+        let len = self.values.len();
 
-    /// Applies a Partner move.
-    fn partner(&mut self, first: char, second: char) {
-        let first = self
-            .values
-            .iter()
-            .enumerate()
-            .find(|&(_, &elt)| elt == first)
-            .unwrap();
+        for mv in moves {
+            mv.check(len);
 
-        let second = self
-            .values
-            .iter()
-            .enumerate()
-            .find(|&(_, &elt)| elt == second)
-            .unwrap();
+            match *mv {
+                Move::Spin(x) => self.values.rotate_right(x % len),
+                Move::Exchange(x, y) => self.values.swap(x, y),
+                Move::Partner(x, y) => {
+                    let x = self.values.iter().position(|&elt| elt == x).unwrap();
+                    let y = self.values.iter().position(|&elt| elt == y).unwrap();
 
-        self.exchange(first.0, second.0);
+                    self.values.swap(x, y);
+                }
+            }
+        }
     }
 }
 
-// implement Display to get the to_string() method
-impl std::fmt::Display for Dance {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s: String = self.values.iter().collect();
-        write!(f, "{}", s)
+/// How to repeat the dance.
+enum Mode {
+    Algebra,        // raise the permutations of the dance to the power of the rounds
+    CycleDetection, // dance until a line repeats, and skip the whole cycles
+}
+
+/// Returns the line of `programs` programs after `rounds` rounds of the moves.
+fn dance(moves: &[Move], programs: usize, rounds: u64, mode: Mode) -> String {
+    match mode {
+        Mode::Algebra => Dance::new(moves, programs).pow(rounds).line(),
+        Mode::CycleDetection => {
+            let mut line = Line::new(programs);
+            let mut seen: HashMap<String, u64> = HashMap::new();
+            let mut lines: Vec<String> = Vec::new();
+
+            for round in 0..rounds {
+                let current: String = line.values.iter().collect();
+
+                if let Some(&start) = seen.get(&current) {
+                    let period = round - start;
+                    return lines[(start + (rounds - start) % period) as usize].clone();
+                }
+
+                seen.insert(current.clone(), round);
+                lines.push(current);
+
+                line.apply_moves(moves);
+            }
+
+            line.values.iter().collect()
+        }
     }
 }
 
@@ -134,27 +257,110 @@ fn main() {
 
     // --- Part One --- //
 
-    let mut dance = Dance::new();
-
-    dance.apply_moves(&moves);
-
-    let part_one = dance.to_string();
+    let part_one = dance(&moves, PROGRAMS, 1, Mode::Algebra);
 
     println!("Part One: {}", part_one);
 
     // --- Part Two --- //
 
-    // find the number of iterations after which the
-    // dance returns to the initial state
-    let period = Dance::period(&moves);
+    // the rounds are repeated by cycle detection when the `--cycle` flag is given
+    let mode = match std::env::args().any(|arg| arg == "--cycle") {
+        true => Mode::CycleDetection,
+        false => Mode::Algebra,
+    };
+
+    let part_two = dance(&moves, PROGRAMS, ROUNDS, mode);
+
+    println!("Part Two: {}", part_two);
+}
 
-    let mut dance = Dance::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    for _ in 0..(1_000_000_000 % period) {
-        dance.apply_moves(&moves);
+    fn example() -> Vec<Move> {
+        "s1,x3/4,pe/b".split(',').map(Move::from).collect()
     }
 
-    let part_two = dance.to_string();
+    #[test]
+    fn example_rounds() {
+        assert_eq!("baedc", dance(&example(), 5, 1, Mode::Algebra));
+        assert_eq!("ceadb", dance(&example(), 5, 2, Mode::Algebra));
 
-    println!("Part Two: {}", part_two);
+        assert_eq!("baedc", dance(&example(), 5, 1, Mode::CycleDetection));
+        assert_eq!("ceadb", dance(&example(), 5, 2, Mode::CycleDetection));
+    }
+
+    #[test]
+    fn modes() {
+        let moves: Vec<Move> = "x0/3,pa/c,s3,x1/4,pb/e,s1,pd/a"
+            .split(',')
+            .map(Move::from)
+            .collect();
+
+        for rounds in [0, 1, 7, 1000, ROUNDS] {
+            assert_eq!(
+                dance(&moves, 5, rounds, Mode::CycleDetection),
+                dance(&moves, 5, rounds, Mode::Algebra)
+            );
+        }
+    }
+
+    #[test]
+    fn then() {
+        // two rounds are the dance followed by itself
+        let round = Dance::new(&example(), 5);
+
+        assert_eq!(round.then(&round), round.pow(2));
+        assert_eq!(round.then(&round).then(&round), round.pow(3));
+        assert_eq!(Dance::identity(5), round.pow(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one program")]
+    fn no_programs() {
+        dance(&example(), 0, 1, Mode::CycleDetection);
+    }
+
+    #[test]
+    fn all_letters() {
+        let moves: Vec<Move> = "s1,pa/z".split(',').map(Move::from).collect();
+        let expected = "azbcdefghijklmnopqrstuvwxy";
+
+        assert_eq!(expected, dance(&moves, MAX_PROGRAMS, 1, Mode::Algebra));
+        assert_eq!(
+            expected,
+            dance(&moves, MAX_PROGRAMS, 1, Mode::CycleDetection)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 26 programs")]
+    fn too_many_programs() {
+        Dance::identity(MAX_PROGRAMS + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 26 programs")]
+    fn too_many_programs_in_line() {
+        Line::new(MAX_PROGRAMS + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "exchange x1/5 is out of the line of 5 programs")]
+    fn exchange_out_of_line() {
+        dance(&[Move::Exchange(1, 5)], 5, 1, Mode::Algebra);
+    }
+
+    #[test]
+    #[should_panic(expected = "exchange x5/0 is out of the line of 5 programs")]
+    fn exchange_out_of_line_cycle() {
+        dance(&[Move::Exchange(5, 0)], 5, 1, Mode::CycleDetection);
+    }
+
+    #[test]
+    #[should_panic(expected = "program f is not in the dance")]
+    fn partner_out_of_line() {
+        dance(&[Move::Partner('a', 'f')], 5, 1, Mode::CycleDetection);
+    }
 }
@@ -0,0 +1,353 @@
+use std::fmt;
+
+use crate::{Arg, Cmd, Program, Queue, Status};
+use arithmetic::{Arithmetic, RuntimeError};
+
+/// Enum representing how the programs are connected,
+/// that is where the values sent by each program go.
+#[derive(Debug, Clone, Copy)]
+pub enum Topology {
+    Ring,      // program p sends to program p + 1, and the last one to the first one
+    Pairwise,  // programs 2k and 2k + 1 send to each other
+    Broadcast, // every program sends to all the others
+}
+
+impl Topology {
+    /// Returns the programs receiving the values sent by `program`,
+    /// out of `count` programs.
+    pub fn destinations(&self, program: usize, count: usize) -> Vec<usize> {
+        match self {
+            Topology::Ring => vec![(program + 1) % count],
+            Topology::Pairwise => vec![program ^ 1],
+            Topology::Broadcast => (0..count).filter(|&p| p != program).collect(),
+        }
+    }
+}
+
+/// Enum representing how the scheduler chooses the next program to run.
+#[derive(Debug, Clone, Copy)]
+pub enum Policy {
+    RoundRobin,
+    Random(u64), // seed of the random generator
+}
+
+/// Xorshift pseudorandom number generator, good enough to shuffle the schedule.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+
+        self.0
+    }
+}
+
+/// State of a program between two runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum State {
+    Ready(i64),   // index of the next command
+    Blocked(i64), // index of the 'rcv' command waiting for a value
+    Done,
+}
+
+/// A 'rcv' command on which a program is blocked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blocked {
+    pub program: usize,
+    pub index: usize,
+    pub arg: Arg,
+}
+
+/// Enum representing how the programs stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Terminated,             // every program terminated
+    Deadlock(Vec<Blocked>), // the programs which did not terminate are all blocked
+}
+
+/// Counts of a program at the end of the run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Counts {
+    pub sent: u32,
+    pub received: u32,
+    pub queued: usize, // values left in the queue of the program
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub programs: Vec<Counts>,
+}
+
+impl Report {
+    /// Creates the report of the programs in their final `states`,
+    /// with `queued` values left in their queues.
+    pub(crate) fn new<A: Arithmetic>(
+        cmds: &[Cmd],
+        programs: &[Program<A>],
+        states: &[State],
+        queued: Vec<usize>,
+    ) -> Self {
+        let blocked: Vec<Blocked> = (0..programs.len())
+            .filter_map(|p| match states[p] {
+                State::Blocked(idx) => match &cmds[idx as usize] {
+                    Cmd::Rcv(arg) => Some(Blocked {
+                        program: p,
+                        index: idx as usize,
+                        arg: *arg,
+                    }),
+                    _ => unreachable!(),
+                },
+                _ => None,
+            })
+            .collect();
+
+        let outcome = match blocked.is_empty() {
+            true => Outcome::Terminated,
+            false => Outcome::Deadlock(blocked),
+        };
+
+        let programs = programs
+            .iter()
+            .zip(queued)
+            .map(|(program, queued)| Counts {
+                sent: program.send_count,
+                received: program.receive_count,
+                queued,
+            })
+            .collect();
+
+        Self { outcome, programs }
+    }
+
+    /// Returns the number of values sent by each program.
+    pub fn sent(&self) -> Vec<u32> {
+        self.programs.iter().map(|counts| counts.sent).collect()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Terminated => writeln!(f, "All programs terminated.")?,
+            Outcome::Deadlock(blocked) => {
+                writeln!(f, "Deadlock:")?;
+
+                for b in blocked {
+                    writeln!(
+                        f,
+                        "  program {} blocked on 'rcv {}' at index {}",
+                        b.program, b.arg, b.index
+                    )?;
+                }
+            }
+        }
+
+        for (p, counts) in self.programs.iter().enumerate() {
+            writeln!(
+                f,
+                "  program {}: sent {}, received {}, queue length {}",
+                p, counts.sent, counts.received, counts.queued
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Creates `count` programs, where program p has the value p in register 'p'.
+pub(crate) fn programs<A: Arithmetic>(count: usize) -> Vec<Program<A>> {
+    (0..count)
+        .map(|p| {
+            let mut program = Program::new();
+            program
+                .cmd_set(&Arg::Reg('p'), &Arg::Int(p as i64))
+                .unwrap();
+            program
+        })
+        .collect()
+}
+
+/// Scheduler running programs which communicate through queues.
+///
+/// Each program has its own queue of received values. The values sent by
+/// a program are added to the queues of its destinations, given by the topology.
+/// Program p starts with the value p in register 'p'.
+///
+/// A program runs until it terminates or waits for a value. The scheduler
+/// then picks another program which can make progress, until there are none left.
+pub struct Scheduler<'a, A: Arithmetic> {
+    cmds: &'a [Cmd],
+    topology: Topology,
+    programs: Vec<Program<A>>,
+    queues: Vec<Queue<A::Word>>,
+    states: Vec<State>,
+}
+
+impl<'a, A: Arithmetic> Scheduler<'a, A> {
+    pub fn new(cmds: &'a [Cmd], count: usize, topology: Topology) -> Self {
+        assert!(count > 0, "no programs to run");
+
+        if let Topology::Pairwise = topology {
+            assert!(
+                count.is_multiple_of(2),
+                "pairwise topology with an odd number of programs"
+            );
+        }
+
+        Self {
+            cmds,
+            topology,
+            programs: programs(count),
+            queues: vec![Queue::new(); count],
+            states: vec![State::Ready(0); count],
+        }
+    }
+
+    /// Returns `true` if the program can make progress.
+    fn is_runnable(&self, p: usize) -> bool {
+        match self.states[p] {
+            State::Ready(_) => true,
+            State::Blocked(_) => !self.queues[p].is_empty(),
+            State::Done => false,
+        }
+    }
+
+    /// Runs the program `p` until it terminates or waits for a value,
+    /// and delivers the values it sent.
+    fn run_program(&mut self, p: usize) -> Result<(), RuntimeError<Cmd>> {
+        let idx = match self.states[p] {
+            State::Ready(idx) | State::Blocked(idx) => idx,
+            State::Done => return Ok(()),
+        };
+
+        let mut sent = Queue::new();
+
+        let status = self.programs[p].run(self.cmds, idx, &mut sent, &mut self.queues[p])?;
+
+        self.states[p] = match status {
+            Status::Wait(idx) => State::Blocked(idx),
+            Status::Term => State::Done,
+        };
+
+        for dest in self.topology.destinations(p, self.programs.len()) {
+            self.queues[dest].extend(sent.iter().cloned());
+        }
+
+        Ok(())
+    }
+
+    /// Runs the programs until every program has either terminated or is blocked.
+    ///
+    /// Returns an error if an operation fails in one of the programs.
+    pub fn run(&mut self, policy: Policy) -> Result<Report, RuntimeError<Cmd>> {
+        let count = self.programs.len();
+
+        let mut rng = match policy {
+            Policy::Random(seed) => Xorshift(seed.max(1)), // xorshift is stuck on 0
+            Policy::RoundRobin => Xorshift(1),
+        };
+
+        let mut next = 0; // next program in the round-robin order
+
+        loop {
+            let runnable: Vec<usize> = (0..count).filter(|&p| self.is_runnable(p)).collect();
+
+            if runnable.is_empty() {
+                break;
+            }
+
+            let p = match policy {
+                Policy::RoundRobin => *runnable
+                    .iter()
+                    .find(|&&p| p >= next)
+                    .unwrap_or(&runnable[0]),
+                Policy::Random(_) => runnable[(rng.next() % runnable.len() as u64) as usize],
+            };
+
+            self.run_program(p)?;
+
+            next = p + 1;
+        }
+
+        Ok(self.report())
+    }
+
+    fn report(&self) -> Report {
+        let queued = self.queues.iter().map(|queue| queue.len()).collect();
+
+        Report::new(self.cmds, &self.programs, &self.states, queued)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arithmetic::Checked;
+
+    // every program sends three values, then waits for a fourth one
+    const EXAMPLE: &str = "snd 1\nsnd 2\nsnd p\nrcv a\nrcv b\nrcv c\nrcv d";
+
+    #[test]
+    fn random_schedule() {
+        let cmds: Vec<Cmd> = EXAMPLE.lines().map(Cmd::from).collect();
+
+        // the programs only communicate through the queues,
+        // so the order in which they are run does not change the outcome
+        for (count, topology) in [
+            (2, Topology::Pairwise),
+            (3, Topology::Ring),
+            (4, Topology::Ring),
+            (3, Topology::Broadcast),
+        ] {
+            let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, count, topology);
+            let expected = scheduler.run(Policy::RoundRobin).unwrap();
+
+            for seed in 1..=10 {
+                let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, count, topology);
+                assert_eq!(expected, scheduler.run(Policy::Random(seed)).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn pairwise_deadlock() {
+        let cmds: Vec<Cmd> = EXAMPLE.lines().map(Cmd::from).collect();
+
+        let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, 2, Topology::Pairwise);
+        let report = scheduler.run(Policy::RoundRobin).unwrap();
+
+        // both programs receive the three values of the other, then wait on 'rcv d'
+        let blocked = |program| Blocked {
+            program,
+            index: 6,
+            arg: Arg::Reg('d'),
+        };
+        assert_eq!(
+            Outcome::Deadlock(vec![blocked(0), blocked(1)]),
+            report.outcome
+        );
+
+        let counts = Counts {
+            sent: 3,
+            received: 3,
+            queued: 0,
+        };
+        assert_eq!(vec![counts, counts], report.programs);
+        assert_eq!(vec![3, 3], report.sent());
+    }
+
+    #[test]
+    fn broadcast() {
+        let cmds: Vec<Cmd> = EXAMPLE.lines().map(Cmd::from).collect();
+
+        let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, 3, Topology::Broadcast);
+        let report = scheduler.run(Policy::RoundRobin).unwrap();
+
+        // each program receives six values, more than it waits for
+        assert_eq!(Outcome::Terminated, report.outcome);
+        assert_eq!(vec![3, 3, 3], report.sent());
+    }
+}
@@ -1,12 +1,24 @@
 use std::collections::{HashMap, VecDeque};
 
-type Queue = VecDeque<i64>;
+// The duet module contains the scheduler running several programs connected by queues.
+pub mod duet;
+
+// The threaded module runs each program of the duet on its own thread.
+pub mod threaded;
+
+use arithmetic::{Arithmetic, Checked, Fault, RuntimeError};
+use duet::{Policy, Scheduler, Topology};
+
+type Queue<W> = VecDeque<W>;
+
+/// Binary operation on the registers, such as `Arithmetic::add`.
+type Op<W> = fn(&W, &W) -> Result<W, Fault>;
 
 /// Enum representing an argument to a command.
 ///
 /// The arguments can be either integers or registers.
-#[derive(Clone, Copy)]
-enum Arg {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Arg {
     Int(i64),
     Reg(char),
 }
@@ -20,11 +32,20 @@ impl From<&str> for Arg {
     }
 }
 
+impl std::fmt::Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::Int(int) => write!(f, "{}", int),
+            Arg::Reg(reg) => write!(f, "{}", reg),
+        }
+    }
+}
+
 /// Enum representing the possible commands (= instructions).
 ///
 /// Each command takes one or two arguments of type `Arg`.
-#[derive(Clone)]
-enum Cmd {
+#[derive(Debug, Clone)]
+pub enum Cmd {
     Snd(Arg),      // (IoR)
     Rcv(Arg),      // (IoR)
     Set(Arg, Arg), // (Reg, IoR)
@@ -62,34 +83,23 @@ impl From<&str> for Cmd {
 /// The program exits with the `Term` status when the index goes
 /// out of bounds, which means that the program has terminated.
 #[derive(PartialEq, Debug)]
-enum Status {
+pub enum Status {
     Wait(i64),
     Term,
 }
 
-impl Status {
-    /// Returns the index stored in the status.
-    ///
-    /// Returns -1 for `Term` so that calling Program::run() with
-    /// this value does nothing and returns `Term` once more.
-    fn index(&self) -> i64 {
-        match self {
-            Status::Wait(idx) => *idx,
-            Status::Term => -1,
-        }
-    }
-}
-
-struct Program {
-    registers: HashMap<char, i64>,
+pub struct Program<A: Arithmetic> {
+    registers: HashMap<char, A::Word>,
     send_count: u32,
+    receive_count: u32,
 }
 
-impl Program {
+impl<A: Arithmetic> Program<A> {
     fn new() -> Self {
         Self {
             registers: HashMap::new(),
             send_count: 0,
+            receive_count: 0,
         }
     }
 
@@ -98,70 +108,71 @@ impl Program {
     /// If the argument is an `Int`, returns its value.
     /// If the argument is a `Reg`, returns the value in the register.
     /// If the register is not initialized, returns the starting value 0.
-    fn get_value(&self, arg: &Arg) -> i64 {
+    fn get_value(&self, arg: &Arg) -> Result<A::Word, Fault> {
         match *arg {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => *self.registers.get(&reg).unwrap_or(&0),
+            Arg::Int(int) => A::word(int),
+            Arg::Reg(reg) => Ok(self.registers.get(&reg).cloned().unwrap_or(A::zero())),
         }
     }
 
+    /// Applies the operation `op` to the register `arg0` and the value of `arg1`.
+    /// Note: Does nothing if `arg0` is not a register.
+    fn apply(&mut self, arg0: &Arg, arg1: &Arg, op: Op<A::Word>) -> Result<(), Fault> {
+        if let Arg::Reg(reg) = *arg0 {
+            let val = op(&self.get_value(arg0)?, &self.get_value(arg1)?)?;
+            self.registers.insert(reg, val);
+        }
+
+        Ok(())
+    }
+
     /// Executes the 'snd' command.
     ///
     /// Sends the value of the argurment to the back of the queue.
-    fn cmd_snd(&mut self, arg: &Arg, queue: &mut Queue) {
-        queue.push_back(self.get_value(arg));
+    fn cmd_snd(&mut self, arg: &Arg, queue: &mut Queue<A::Word>) -> Result<(), Fault> {
+        queue.push_back(self.get_value(arg)?);
         self.send_count += 1;
+
+        Ok(())
     }
 
     /// Executes the 'rcv' command.
     ///
     /// Recovers the value at the front of the queue and stores it in
     /// the register determined by the argument.
-    fn cmd_rcv(&mut self, arg: &Arg, queue: &mut Queue) -> Result<(), ()> {
-        let val = queue.pop_front().ok_or(())?;
+    /// Returns `false` if the queue is empty.
+    fn cmd_rcv(&mut self, arg: &Arg, queue: &mut Queue<A::Word>) -> bool {
+        let Some(val) = queue.pop_front() else {
+            return false;
+        };
 
-        self.cmd_set(arg, &Arg::Int(val));
+        if let Arg::Reg(reg) = *arg {
+            self.registers.insert(reg, val);
+        }
 
-        Ok(())
+        self.receive_count += 1;
+
+        true
     }
 
     /// Executes the 'set' command.
     ///
     /// Sets the value of the register `arg0` to `arg1`.
     /// Note: Does nothing if `arg0` is not a register.
-    fn cmd_set(&mut self, arg0: &Arg, arg1: &Arg) {
+    fn cmd_set(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = *arg0 {
-            self.registers.insert(reg, self.get_value(arg1));
+            self.registers.insert(reg, self.get_value(arg1)?);
         }
-    }
-
-    /// Executes the 'add' command.
-    fn cmd_add(&mut self, arg0: &Arg, arg1: &Arg) {
-        if let Arg::Reg(reg) = *arg0 {
-            *self.registers.entry(reg).or_insert(0) += self.get_value(arg1);
-        }
-    }
 
-    /// Executes the 'mul' command.
-    fn cmd_mul(&mut self, arg0: &Arg, arg1: &Arg) {
-        if let Arg::Reg(reg) = *arg0 {
-            *self.registers.entry(reg).or_insert(0) *= self.get_value(arg1);
-        }
-    }
-
-    /// Executes the 'mod' command.
-    fn cmd_mod(&mut self, arg0: &Arg, arg1: &Arg) {
-        if let Arg::Reg(reg) = *arg0 {
-            *self.registers.entry(reg).or_insert(0) %= self.get_value(arg1);
-        }
+        Ok(())
     }
 
     /// Executes the 'jnz' command.
     /// Returns the offset (relative position) to the next index.
-    fn cmd_jgz(&self, arg0: &Arg, arg1: &Arg) -> i64 {
-        match self.get_value(arg0) {
-            1.. => self.get_value(arg1),
-            _ => 1,
+    fn cmd_jgz(&self, arg0: &Arg, arg1: &Arg) -> Result<i64, Fault> {
+        match self.get_value(arg0)? > A::zero() {
+            true => A::int(&self.get_value(arg1)?),
+            false => Ok(1),
         }
     }
 
@@ -170,26 +181,31 @@ impl Program {
     /// Values sent by 'snd' commands are added to the back of the `snd_queue`.
     /// Values recovered by 'rcv' commands are taken from the front of the `rcv_queue`.
     ///
-    /// Returns a `Result` whose `Ok` variant contains the offset (relative position)
-    /// of the next instruction. (This is 1 for all but the 'jgz' command).
-    /// Returns an `Err` when trying to recover a value from an empty `rcv_queue`.
+    /// Returns the offset (relative position) of the next instruction.
+    /// (This is 1 for all but the 'jgz' command).
+    /// Returns `None` when trying to recover a value from an empty `rcv_queue`,
+    /// and an error if an operation fails.
     fn execute(
         &mut self,
         cmd: &Cmd,
-        snd_queue: &mut Queue,
-        rcv_queue: &mut Queue,
-    ) -> Result<i64, ()> {
+        snd_queue: &mut Queue<A::Word>,
+        rcv_queue: &mut Queue<A::Word>,
+    ) -> Result<Option<i64>, Fault> {
         match cmd {
-            Cmd::Snd(arg) => self.cmd_snd(arg, snd_queue),
-            Cmd::Rcv(arg) => self.cmd_rcv(arg, rcv_queue)?,
-            Cmd::Set(arg0, arg1) => self.cmd_set(arg0, arg1),
-            Cmd::Add(arg0, arg1) => self.cmd_add(arg0, arg1),
-            Cmd::Mul(arg0, arg1) => self.cmd_mul(arg0, arg1),
-            Cmd::Mod(arg0, arg1) => self.cmd_mod(arg0, arg1),
-            Cmd::Jgz(arg0, arg1) => return Ok(self.cmd_jgz(arg0, arg1)),
+            Cmd::Snd(arg) => self.cmd_snd(arg, snd_queue)?,
+            Cmd::Rcv(arg) => {
+                if !self.cmd_rcv(arg, rcv_queue) {
+                    return Ok(None);
+                }
+            }
+            Cmd::Set(arg0, arg1) => self.cmd_set(arg0, arg1)?,
+            Cmd::Add(arg0, arg1) => self.apply(arg0, arg1, A::add)?,
+            Cmd::Mul(arg0, arg1) => self.apply(arg0, arg1, A::mul)?,
+            Cmd::Mod(arg0, arg1) => self.apply(arg0, arg1, A::rem)?,
+            Cmd::Jgz(arg0, arg1) => return self.cmd_jgz(arg0, arg1).map(Some),
         }
 
-        Ok(1)
+        Ok(Some(1))
     }
 
     /// Runs the program until it terminates or waits for a value.
@@ -203,23 +219,34 @@ impl Program {
     ///
     /// In the second case, the returned `Status` contains the index of
     /// the 'rcv' command that could not be executed.
+    ///
+    /// Returns an error with the index of the command if an operation fails.
     fn run(
         &mut self,
         cmds: &[Cmd],
         start_idx: i64,
-        snd_queue: &mut Queue,
-        rcv_queue: &mut Queue,
-    ) -> Status {
+        snd_queue: &mut Queue<A::Word>,
+        rcv_queue: &mut Queue<A::Word>,
+    ) -> Result<Status, RuntimeError<Cmd>> {
         let mut idx = start_idx;
 
         while 0 <= idx && idx < cmds.len() as i64 {
-            match self.execute(&cmds[idx as usize], snd_queue, rcv_queue) {
-                Ok(val) => idx += val,
-                Err(_) => return Status::Wait(idx),
+            let cmd = &cmds[idx as usize];
+
+            match self.execute(cmd, snd_queue, rcv_queue) {
+                Ok(Some(val)) => idx += val,
+                Ok(None) => return Ok(Status::Wait(idx)),
+                Err(fault) => {
+                    return Err(RuntimeError {
+                        fault,
+                        pc: idx as usize,
+                        cmd: cmd.clone(),
+                    })
+                }
             };
         }
 
-        Status::Term
+        Ok(Status::Term)
     }
 }
 
@@ -230,14 +257,17 @@ fn main() {
 
     // --- Part One --- //
 
-    let mut program = Program::new();
+    let mut program: Program<Checked<i64>> = Program::new();
 
     // only store the snd_queue as we never read any value
     let mut queue = Queue::new();
 
     let mut idx = 0;
 
-    while let Status::Wait(out_idx) = program.run(&cmds, idx, &mut queue, &mut Queue::new()) {
+    while let Status::Wait(out_idx) = program
+        .run(&cmds, idx, &mut queue, &mut Queue::new())
+        .unwrap_or_else(|err| panic!("{}", err))
+    {
         // Recover the argument from the command which caused the program to exit.
         // The Waiting status is only returned on a 'rcv' command.
         let arg = match &cmds[out_idx as usize] {
@@ -246,7 +276,11 @@ fn main() {
         };
 
         // exit the loop when first trying to read a value
-        if program.get_value(arg) != 0 {
+        let value = program
+            .get_value(arg)
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        if value != 0 {
             break;
         }
 
@@ -262,34 +296,15 @@ fn main() {
 
     // --- Part Two --- //
 
-    let mut programs = (Program::new(), Program::new());
-
-    programs.0.cmd_set(&Arg::Reg('p'), &Arg::Int(0));
-    programs.1.cmd_set(&Arg::Reg('p'), &Arg::Int(1));
-
-    // snd_queues for each program (and also rec_queue for the other program)
-    let mut queues = (Queue::new(), Queue::new());
-
-    let mut idx = (0, 0); // starting indices
-    let mut status = (Status::Wait(0), Status::Wait(0)); // dummy starting values
+    // Program 0 sends its values to program 1, and the other way around.
+    let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, 2, Topology::Pairwise);
+    let report = scheduler
+        .run(Policy::RoundRobin)
+        .unwrap_or_else(|err| panic!("{}", err));
 
-    while status.0 != Status::Term || status.1 != Status::Term {
-        // run both programs until lock or termination
-        status.0 = programs.0.run(&cmds, idx.0, &mut queues.0, &mut queues.1);
-        status.1 = programs.1.run(&cmds, idx.1, &mut queues.1, &mut queues.0);
-
-        // recover the exit index of each program
-        let exit_idx = (status.0.index(), status.1.index());
-
-        // we reach a deadlock if both queues are empty
-        if queues.0.is_empty() && queues.1.is_empty() {
-            break;
-        }
-
-        idx = exit_idx;
-    }
+    println!("{}", report);
 
-    let part_two = programs.1.send_count;
+    let part_two = report.programs[1].sent;
 
     println!("Part Two: {}", part_two);
 }
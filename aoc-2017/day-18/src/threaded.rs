@@ -0,0 +1,215 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::duet::{self, Report, State, Topology};
+use crate::{Cmd, Program, Queue, Status};
+use arithmetic::{Arithmetic, RuntimeError};
+
+// Each program runs on its own thread and receives its values on a channel.
+// A program runs with a local queue until it waits for a value, then blocks
+// on its channel until another program sends a value.
+//
+// The threads keep a tally of the programs waiting for a value and of the values
+// sent to each program and not received yet. When every program has either
+// terminated or is waiting with nothing sent to it, no program can ever make
+// progress again: the monitor then stops the waiting programs.
+
+/// Message sent on the channel of a program.
+enum Msg<W> {
+    Value(W),
+    Stop, // sent by the monitor on a deadlock
+}
+
+/// State of the programs shared by the threads.
+struct Tally {
+    waiting: Vec<bool>,
+    done: Vec<bool>,
+    pending: Vec<usize>, // values sent to each program and not received yet
+}
+
+impl Tally {
+    /// Returns `true` if no program can make progress.
+    fn is_stalled(&self) -> bool {
+        (0..self.done.len()).all(|p| self.done[p] || (self.waiting[p] && self.pending[p] == 0))
+    }
+}
+
+struct Shared {
+    tally: Mutex<Tally>,
+    changed: Condvar, // notified on every change of the tally
+}
+
+impl Shared {
+    fn update(&self, f: impl FnOnce(&mut Tally)) {
+        f(&mut self.tally.lock().unwrap());
+        self.changed.notify_all();
+    }
+}
+
+/// Runs the program `p` on the current thread.
+///
+/// Returns the program along with its final state,
+/// or an error if an operation fails.
+fn worker<A: Arithmetic>(
+    p: usize,
+    cmds: &[Cmd],
+    mut program: Program<A>,
+    receiver: Receiver<Msg<A::Word>>,
+    senders: Vec<(usize, Sender<Msg<A::Word>>)>,
+    shared: &Shared,
+) -> Result<(Program<A>, State), RuntimeError<Cmd>> {
+    let mut queue = Queue::new();
+    let mut idx = 0;
+
+    loop {
+        let mut sent = Queue::new();
+        let status = program.run(cmds, idx, &mut sent, &mut queue);
+
+        // count the values before sending them, so that they are never missed by the monitor
+        shared.update(|tally| {
+            for (dest, _) in &senders {
+                tally.pending[*dest] += sent.len();
+            }
+        });
+
+        for (_, sender) in &senders {
+            for val in &sent {
+                // the destination may have terminated: its values stay pending
+                let _ = sender.send(Msg::Value(val.clone()));
+            }
+        }
+
+        let wait_idx = match status {
+            Ok(Status::Wait(wait_idx)) => wait_idx,
+            Ok(Status::Term) | Err(_) => {
+                shared.update(|tally| tally.done[p] = true);
+
+                return status.map(|_| (program, State::Done));
+            }
+        };
+
+        shared.update(|tally| tally.waiting[p] = true);
+
+        match receiver.recv() {
+            Ok(Msg::Value(val)) => {
+                shared.update(|tally| {
+                    tally.waiting[p] = false;
+                    tally.pending[p] -= 1;
+                });
+
+                queue.push_back(val);
+                idx = wait_idx;
+            }
+            Ok(Msg::Stop) | Err(_) => return Ok((program, State::Blocked(wait_idx))),
+        }
+    }
+}
+
+/// Runs `count` programs, each on its own thread, connected according to the topology.
+///
+/// This gives the same report as `Scheduler::run`.
+/// Returns an error if an operation fails in one of the programs.
+pub fn run<A>(cmds: &[Cmd], count: usize, topology: Topology) -> Result<Report, RuntimeError<Cmd>>
+where
+    A: Arithmetic,
+    A::Word: Send,
+{
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..count).map(|_| mpsc::channel()).unzip();
+
+    let shared = Shared {
+        tally: Mutex::new(Tally {
+            waiting: vec![false; count],
+            done: vec![false; count],
+            pending: vec![0; count],
+        }),
+        changed: Condvar::new(),
+    };
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = duet::programs::<A>(count)
+            .into_iter()
+            .zip(receivers)
+            .enumerate()
+            .map(|(p, (program, receiver))| {
+                let dests = topology
+                    .destinations(p, count)
+                    .into_iter()
+                    .map(|dest| (dest, senders[dest].clone()))
+                    .collect();
+
+                let shared = &shared;
+                scope.spawn(move || worker(p, cmds, program, receiver, dests, shared))
+            })
+            .collect();
+
+        // monitor: wait until no program can make progress, then stop the waiting ones
+        let tally = shared.tally.lock().unwrap();
+        let tally = shared
+            .changed
+            .wait_while(tally, |tally| !tally.is_stalled());
+        drop(tally.unwrap());
+
+        for sender in &senders {
+            let _ = sender.send(Msg::Stop);
+        }
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    let mut programs = Vec::with_capacity(count);
+    let mut states = Vec::with_capacity(count);
+
+    for result in results {
+        let (program, state) = result?;
+        programs.push(program);
+        states.push(state);
+    }
+
+    let queued = shared.tally.into_inner().unwrap().pending;
+
+    Ok(Report::new(cmds, &programs, &states, queued))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duet::{Outcome, Policy, Scheduler};
+    use arithmetic::Checked;
+
+    // every program sends three values, then waits for a fourth one
+    const EXAMPLE: &str = "snd 1\nsnd 2\nsnd p\nrcv a\nrcv b\nrcv c\nrcv d";
+
+    #[test]
+    fn same_report_as_scheduler() {
+        let cmds: Vec<Cmd> = EXAMPLE.lines().map(Cmd::from).collect();
+
+        for (count, topology) in [
+            (2, Topology::Pairwise),
+            (3, Topology::Ring),
+            (4, Topology::Ring),
+            (3, Topology::Broadcast),
+        ] {
+            let mut scheduler: Scheduler<Checked<i64>> = Scheduler::new(&cmds, count, topology);
+            let expected = scheduler.run(Policy::RoundRobin).unwrap();
+
+            // the threads are scheduled differently on each run
+            for _ in 0..20 {
+                let report = run::<Checked<i64>>(&cmds, count, topology).unwrap();
+                assert_eq!(expected, report);
+            }
+        }
+    }
+
+    #[test]
+    fn example() {
+        let cmds: Vec<Cmd> = EXAMPLE.lines().map(Cmd::from).collect();
+        let report = run::<Checked<i64>>(&cmds, 2, Topology::Pairwise).unwrap();
+
+        assert!(matches!(report.outcome, Outcome::Deadlock(_)));
+        assert_eq!(vec![3, 3], report.sent());
+    }
+}
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
 use std::ops::{Add, Sub};
+use walker::{Point, Walker};
 
 #[derive(PartialEq, Debug)]
 enum Tile {
@@ -147,10 +148,19 @@ fn main() {
 
     let mut walker = GridWalker::from(&input[..]);
 
-    let mut step_count = 0;
+    // record the path of the packet, with the rows pointing down
+    let lattice = |vec: Vector| Point::new(vec.col, -vec.row);
+    let mut path = Walker::new(lattice(walker.pos));
 
-    for _ in walker.by_ref() {
-        step_count += 1;
+    let mut prev = walker.pos;
+
+    while let Some(pos) = walker.next() {
+        // the last step leaves the tubes
+        if walker.map.contains_key(&pos) {
+            path.step(lattice(pos) - lattice(prev));
+        }
+
+        prev = pos;
     }
 
     // --- Part One --- //
@@ -161,7 +171,7 @@ fn main() {
 
     // --- Part Two --- //
 
-    let part_two = step_count;
+    let part_two = path.path().len();
 
     println!("Part Two: {}", part_two);
 }
@@ -1,9 +1,11 @@
-use std::collections::{HashMap, HashSet};
-use std::ops::Sub;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::fmt;
+use std::ops::Add;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Vector {
-    vals: [i32; 3], // [x, y, z]
+    vals: [i64; 3], // [x, y, z]
 }
 
 impl From<&str> for Vector {
@@ -21,12 +23,12 @@ impl From<&str> for Vector {
     }
 }
 
-impl Sub for Vector {
+impl Add for Vector {
     type Output = Self;
 
-    fn sub(self, rhs: Self) -> Self::Output {
+    fn add(self, rhs: Self) -> Self::Output {
         let vals = (0..3)
-            .map(|idx| self.vals[idx] - rhs.vals[idx])
+            .map(|idx| self.vals[idx] + rhs.vals[idx])
             .collect::<Vec<_>>()
             .try_into()
             .unwrap();
@@ -35,13 +37,37 @@ impl Sub for Vector {
     }
 }
 
+/// Error raised when the coefficients of the collision equations overflow.
+#[derive(Debug)]
+struct Overflow(&'static str); // the coefficient which overflows
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "overflow in {}", self.0)
+    }
+}
+
+impl std::error::Error for Overflow {}
+
 impl Vector {
     /// Computes the l1-norm of the vector.
-    fn norm(&self) -> u32 {
+    fn norm(&self) -> u64 {
         self.vals.iter().map(|val| val.unsigned_abs()).sum()
     }
+
+    /// Computes the difference of two vectors, or `None` if a coordinate overflows.
+    fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        let mut vals = [0; 3];
+
+        for (val, (a, b)) in vals.iter_mut().zip(self.vals.iter().zip(rhs.vals)) {
+            *val = a.checked_sub(b)?;
+        }
+
+        Some(Self { vals })
+    }
 }
 
+#[derive(Clone)]
 struct Particle {
     pos: Vector, // position
     vel: Vector, // velocity
@@ -61,88 +87,191 @@ impl From<&str> for Particle {
 }
 
 impl Particle {
-    /// Computes the time of the collision between two particles.
-    fn collision(&self, other: &Self) -> Option<u32> {
+    /// Computes the position of the particle at time `t`.
+    fn position(&self, t: u64) -> Result<[i128; 3], Overflow> {
+        // t(t+1)/2, halving the even factor first so that the product fits
+        let steps = match t % 2 {
+            0 => (t / 2) as i128 * (t as i128 + 1),
+            _ => t as i128 * ((t as i128 + 1) / 2),
+        };
+        let t = t as i128;
+        let mut position = [0; 3];
+
+        for (idx, coord) in position.iter_mut().enumerate() {
+            let (p, v, a) = (self.pos.vals[idx], self.vel.vals[idx], self.acc.vals[idx]);
+
+            *coord = t
+                .checked_mul(v as i128)
+                .zip(steps.checked_mul(a as i128))
+                .and_then(|(vt, at)| (p as i128).checked_add(vt)?.checked_add(at))
+                .ok_or(Overflow("position"))?;
+        }
+
+        Ok(position)
+    }
+
+    /// Computes the time of the first collision between two particles.
+    fn collision(&self, other: &Self) -> Result<Option<u64>, Overflow> {
         // Two particles P = (p, v, a) and P' = (p', v', a') collide at time t
         // iff: 2dp + t * 2dv + t(t+1) * da = 0 (where dx := x - x').
         // This gives three quadratic equations (one per coordinates) that t
-        // must satisfy, under the condition that t is an integer >= 1.
+        // must satisfy, under the condition that t is an integer >= 0.
 
-        let d_pos = self.pos - other.pos;
-        let d_vel = self.vel - other.vel;
-        let d_acc = self.acc - other.acc;
+        let diff = |a: &Vector, b: &Vector| a.checked_sub(b).ok_or(Overflow("difference"));
 
-        // Store the roots of all three equations and their number of occurences (<= 3).
-        let mut roots = HashMap::new();
+        let d_pos = diff(&self.pos, &other.pos)?;
+        let d_vel = diff(&self.vel, &other.vel)?;
+        let d_acc = diff(&self.acc, &other.acc)?;
 
-        // Count the number of nontrivial equations.
-        // The collision times are the simultaneous solutions to all nontrivial equations.
-        let mut nontrivial_eqs = 3;
+        // The collision times are the simultaneous solutions to all nontrivial equations,
+        // or any time if all equations are trivial (None = no constraint yet).
+        let mut times: Option<Vec<u64>> = None;
 
         for idx in 0..3 {
             // write the equation as a * t^2 + b * t + c = 0
             let a = d_acc.vals[idx];
-            let b = 2 * d_vel.vals[idx] + d_acc.vals[idx];
-            let c = 2 * d_pos.vals[idx];
+            let b = d_vel.vals[idx]
+                .checked_mul(2)
+                .and_then(|b| b.checked_add(d_acc.vals[idx]))
+                .ok_or(Overflow("linear coefficient"))?;
+            let c = d_pos.vals[idx]
+                .checked_mul(2)
+                .ok_or(Overflow("constant coefficient"))?;
 
             // ignore the trivial equations (a = b = c = 0)
             if a | b | c == 0 {
-                nontrivial_eqs -= 1;
                 continue;
             }
 
-            // solve the quadratic equation and insert the roots into the map
-            for root in solve_quadratic_equation(a, b, c).into_iter() {
-                *roots.entry(root).or_insert(0) += 1;
-            }
+            let roots = solve_quadratic_equation(a, b, c)?;
+
+            times = match times {
+                None => Some(roots),
+                Some(times) => Some(times.into_iter().filter(|t| roots.contains(t)).collect()),
+            };
         }
 
-        // find the smallest t in the map which is solution to all nontrivial equations
-        roots
-            .into_iter()
-            .filter_map(|(t, count)| {
-                if count == nontrivial_eqs {
-                    Some(t)
-                } else {
-                    None
-                }
-            })
-            .min()
+        Ok(match times {
+            None => Some(0), // identical particles
+            Some(times) => times.into_iter().min(),
+        })
     }
 }
 
-/// Solves the quadratic equation ax^2 + bx + c = 0.
+/// Solves the quadratic equation ax^2 + bx + c = 0 exactly.
 ///
-/// Returns the non-negative integer roots.
+/// Returns the non-negative integer roots in ascending order.
 ///
 /// This function assumes that the equation is not trivial, i.e.
 /// that one of a, b, or c is non-zero.
-fn solve_quadratic_equation(a: i32, b: i32, c: i32) -> Vec<u32> {
+fn solve_quadratic_equation(a: i64, b: i64, c: i64) -> Result<Vec<u64>, Overflow> {
+    let (a, b, c) = (a as i128, b as i128, c as i128);
+
     if a == 0 {
-        if (b == 0) || (c % b != 0) || (b * c > 0) {
-            return Vec::new();
+        if (b == 0) || (c % b != 0) || (-c / b < 0) {
+            return Ok(Vec::new());
         }
 
-        return Vec::from([(-c / b) as u32]);
+        return Ok(Vec::from([(-c / b) as u64]));
+    }
+
+    let delta = a
+        .checked_mul(c)
+        .and_then(|ac| ac.checked_mul(4))
+        .and_then(|ac| (b * b).checked_sub(ac))
+        .ok_or(Overflow("discriminant"))?;
+
+    if delta < 0 {
+        return Ok(Vec::new());
+    }
+
+    // the roots are rational iff the discriminant is a perfect square
+    let sqrt = delta.isqrt();
+
+    if sqrt * sqrt != delta {
+        return Ok(Vec::new());
     }
 
-    let (a, b, c) = (a as f32, b as f32, c as f32);
-    let delta = b * b - 4.0 * a * c;
+    // retain only the non-negative integer roots
+    let mut roots: Vec<_> = [-b - sqrt, -b + sqrt]
+        .into_iter()
+        .filter(|num| num % (2 * a) == 0)
+        .map(|num| num / (2 * a))
+        .filter(|&root| root >= 0)
+        .map(|root| root as u64)
+        .collect();
+
+    roots.sort();
+    roots.dedup();
+
+    Ok(roots)
+}
+
+/// Group of particles destroyed together at the same time and position.
+struct CollisionGroup {
+    time: u64,
+    particles: Vec<usize>,
+}
+
+/// Computes the exact collision time of every pair of particles, and processes the
+/// collisions in chronological order, removing the particles collided at each time.
+///
+/// Returns the groups of colliding particles in chronological order.
+/// As all pairwise collisions are known, no collision can happen after the last group.
+fn resolve_collisions(particles: &[Particle]) -> Result<Vec<CollisionGroup>, Overflow> {
+    // queue of pairwise collisions, earliest first
+    let mut queue = BinaryHeap::new();
+
+    for idx1 in 0..particles.len() {
+        for idx2 in (idx1 + 1)..particles.len() {
+            if let Some(time) = particles[idx1].collision(&particles[idx2])? {
+                queue.push(Reverse((time, idx1, idx2)));
+            }
+        }
+    }
+
+    let mut remaining = vec![true; particles.len()];
+    let mut groups = Vec::new();
+
+    while let Some(Reverse((time, idx1, idx2))) = queue.pop() {
+        // gather all collisions happening at the same time
+        let mut pairs = vec![(idx1, idx2)];
+
+        while let Some(&Reverse((next_time, idx1, idx2))) = queue.peek() {
+            if next_time != time {
+                break;
+            }
+
+            pairs.push((idx1, idx2));
+            queue.pop();
+        }
+
+        // group the particles still present by collision position
+        let mut by_position: BTreeMap<[i128; 3], BTreeSet<usize>> = BTreeMap::new();
+
+        for (idx1, idx2) in pairs {
+            if remaining[idx1] & remaining[idx2] {
+                let position = particles[idx1].position(time)?;
+                by_position
+                    .entry(position)
+                    .or_default()
+                    .extend([idx1, idx2]);
+            }
+        }
+
+        for (_, group) in by_position {
+            for &idx in group.iter() {
+                remaining[idx] = false;
+            }
 
-    // retain only the positive integers roots
-    let roots: HashSet<_> = Vec::from([
-        (-b + delta.sqrt()) / (2.0 * a),
-        (-b - delta.sqrt()) / (2.0 * a),
-    ])
-    .into_iter()
-    .filter(|&root| root >= 0.0)
-    .filter_map(|root| match root.fract() {
-        0.0 => Some(root as u32),
-        _ => None,
-    })
-    .collect();
+            groups.push(CollisionGroup {
+                time,
+                particles: group.into_iter().collect(),
+            });
+        }
+    }
 
-    roots.into_iter().collect()
+    Ok(groups)
 }
 
 // The value of the acceleration, velocity, and position
@@ -174,46 +303,177 @@ fn main() {
 
     // --- Part Two --- //
 
-    // list the remaining particles (after collisions)
-    let mut remaining: Vec<_> = (0..particles.len()).map(|_| true).collect();
+    let groups = resolve_collisions(&particles).unwrap_or_else(|err| panic!("{}", err));
 
-    // store all collisions in a map: time -> list of collisions (pairs of indices)
-    let mut collisions = HashMap::new();
+    let collided: usize = groups.iter().map(|group| group.particles.len()).sum();
+    let part_two = particles.len() - collided;
 
-    for idx1 in 0..particles.len() {
-        for idx2 in (idx1 + 1)..particles.len() {
-            if let Some(time) = particles[idx1].collision(&particles[idx2]) {
-                collisions
-                    .entry(time)
-                    .or_insert(Vec::new())
-                    .push((idx1, idx2));
-            }
-        }
+    println!("Part Two: {}", part_two);
+
+    if let Some(last) = groups.last() {
+        println!("Last collision at tick {}", last.time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Step-by-step simulation of the particles, removing colliding particles after each step.
+    ///
+    /// Used as an oracle for the collision engine.
+    struct Simulator {
+        particles: Vec<Particle>,
+        time: u64,
     }
 
-    // list the times at which collisions happen in chronological order
-    let mut collision_times: Vec<_> = collisions.keys().collect();
-    collision_times.sort();
+    impl Simulator {
+        fn new(particles: &[Particle]) -> Self {
+            let mut simulator = Self {
+                particles: particles.to_vec(),
+                time: 0,
+            };
 
-    // go through the collision times and delete any colliding particles
-    for time in collision_times {
-        let mut delete = HashSet::new();
+            simulator.remove_collisions();
 
-        for &(idx1, idx2) in collisions.get(time).unwrap() {
-            // if both particles are still present at time t-1, mark them for deletion
-            if remaining[idx1] & remaining[idx2] {
-                delete.insert(idx1);
-                delete.insert(idx2);
+            simulator
+        }
+
+        fn remove_collisions(&mut self) {
+            let mut count: HashMap<Vector, usize> = HashMap::new();
+
+            for particle in self.particles.iter() {
+                *count.entry(particle.pos).or_insert(0) += 1;
             }
+
+            self.particles.retain(|particle| count[&particle.pos] == 1);
+        }
+
+        /// Updates the velocity and position of each particle, then removes the collisions.
+        fn step(&mut self) {
+            for particle in self.particles.iter_mut() {
+                particle.vel = particle.vel + particle.acc;
+                particle.pos = particle.pos + particle.vel;
+            }
+
+            self.time += 1;
+            self.remove_collisions();
         }
 
-        // remove all particles involved in a collision at time t
-        for idx in delete {
-            remaining[idx] = false;
+        /// Simulates the particles until time `time`.
+        fn run_until(&mut self, time: u64) {
+            while self.time < time {
+                self.step();
+            }
         }
     }
 
-    let part_two = remaining.into_iter().filter(|&rem| rem).count();
+    /// Counts the particles left after the collisions, with the collision engine
+    /// and with the simulation up to the last collision.
+    fn remaining(particles: &[Particle]) -> (usize, usize) {
+        let groups = resolve_collisions(particles).unwrap();
 
-    println!("Part Two: {}", part_two);
+        let collided: usize = groups.iter().map(|group| group.particles.len()).sum();
+        let last_time = groups.last().map_or(0, |group| group.time);
+
+        let mut simulator = Simulator::new(particles);
+        simulator.run_until(last_time);
+
+        (particles.len() - collided, simulator.particles.len())
+    }
+
+    #[test]
+    fn example() {
+        let particles: Vec<Particle> = [
+            "p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>",
+            "p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>",
+            "p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>",
+            "p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>",
+        ]
+        .into_iter()
+        .map(Particle::from)
+        .collect();
+
+        let groups = resolve_collisions(&particles).unwrap();
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].time, 2);
+        assert_eq!(groups[0].particles, vec![0, 1, 2]);
+
+        assert_eq!(remaining(&particles), (1, 1));
+    }
+
+    #[test]
+    fn accelerated_collision() {
+        // the first particle is at t(t+1)/2 on the x-axis, and meets the second at t = 3
+        let first = Particle::from("p=<0,0,0>, v=<0,0,0>, a=<1,0,0>");
+        let second = Particle::from("p=<6,0,0>, v=<0,0,0>, a=<0,0,0>");
+        let third = Particle::from("p=<7,0,0>, v=<0,0,0>, a=<0,0,0>");
+
+        assert_eq!(first.collision(&second).unwrap(), Some(3));
+        assert_eq!(first.collision(&third).unwrap(), None);
+
+        // the first particle is destroyed before it reaches 10
+        let fourth = Particle::from("p=<10,0,0>, v=<0,0,0>, a=<0,0,0>");
+        let particles = [first, second, third, fourth];
+
+        assert_eq!(remaining(&particles), (2, 2));
+    }
+
+    #[test]
+    fn against_simulation() {
+        // small pseudo-random particles, which collide often
+        let mut seed: u64 = 1;
+        let mut next = || {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as i64 % 7 - 3
+        };
+
+        let particles: Vec<Particle> = (0..60)
+            .map(|_| Particle {
+                pos: Vector {
+                    vals: [next(), next(), next()],
+                },
+                vel: Vector {
+                    vals: [next(), next(), next()],
+                },
+                acc: Vector {
+                    vals: [next(), next(), 0],
+                },
+            })
+            .collect();
+
+        let (engine, simulation) = remaining(&particles);
+
+        assert!(engine < particles.len());
+        assert_eq!(engine, simulation);
+    }
+
+    #[test]
+    fn overflow() {
+        let first = Particle::from("p=<0,0,0>, v=<0,0,0>, a=<0,0,0>");
+        let second = Particle::from("p=<0,0,0>, v=<-9223372036854775807,0,0>, a=<0,0,0>");
+
+        assert!(first.collision(&second).is_err());
+        assert!(resolve_collisions(&[first, second]).is_err());
+
+        // the difference of the positions does not fit
+        let first = Particle::from("p=<-9223372036854775808,0,0>, v=<0,0,0>, a=<0,0,0>");
+        let second = Particle::from("p=<1,0,0>, v=<0,0,0>, a=<0,0,0>");
+
+        assert!(matches!(
+            first.collision(&second),
+            Err(Overflow("difference"))
+        ));
+
+        // the position after many steps does not fit
+        let particle = Particle::from("p=<0,0,0>, v=<0,0,0>, a=<0,0,9223372036854775807>");
+
+        assert_eq!([0, 0, 9223372036854775807], particle.position(1).unwrap());
+        assert!(matches!(
+            particle.position(u64::MAX),
+            Err(Overflow("position"))
+        ));
+    }
 }
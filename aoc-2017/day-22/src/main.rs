@@ -1,9 +1,13 @@
-use std::collections::HashMap;
-
+use std::fmt;
 use std::ops::{AddAssign, MulAssign};
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug)]
-struct Complex {
+// The virus module contains the virus moving on the grid, whose state can be saved and restored.
+pub mod virus;
+
+use virus::Virus;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub struct Complex {
     x: i32,
     y: i32,
 }
@@ -24,118 +28,233 @@ impl MulAssign for Complex {
     }
 }
 
-#[derive(PartialEq)]
-enum Status {
-    Clean,
-    Weakened,
-    Infected,
-    Flagged,
+// The behaviour of the virus is given by a rule table, with a line per node state:
+//     name symbol turn next
+// The virus turns according to the state of the current node, which then moves to the
+// next state. The first state is the state of the clean nodes, and the symbols are used
+// to read the input and to draw the grid.
+
+const RULES_V1: &str = "\
+clean    . left     infected
+infected # right    clean";
+
+const RULES_V2: &str = "\
+clean    . left     weakened
+weakened W straight infected
+infected # right    flagged
+flagged  F reverse  clean";
+
+/// Largest number of states, as the grid stores the state of each node in a byte.
+const MAX_STATES: usize = u8::MAX as usize + 1;
+
+/// Turn of the virus, as a multiplication of its direction.
+#[derive(Clone, Copy, Debug)]
+enum Turn {
+    Left,
+    Right,
+    Straight,
+    Reverse,
 }
 
-impl Status {
-    fn update(&self) -> Self {
-        match *self {
-            Status::Clean => Status::Weakened,
-            Status::Weakened => Status::Infected,
-            Status::Infected => Status::Flagged,
-            Status::Flagged => Status::Clean,
+impl Turn {
+    fn factor(&self) -> Complex {
+        match self {
+            Turn::Left => Complex { x: 0, y: 1 },
+            Turn::Right => Complex { x: 0, y: -1 },
+            Turn::Straight => Complex { x: 1, y: 0 },
+            Turn::Reverse => Complex { x: -1, y: 0 },
         }
     }
 }
 
-struct Virus {
-    /// Map containing the position and status of the non-clean nodes.
-    /// All nodes without an entry in this map are clean.
-    /// The positions are relative to the origin of the virus.
-    nodes: HashMap<Complex, Status>,
-
-    pos: Complex,
-    dir: Complex,
+#[derive(Debug)]
+struct Rule {
+    symbol: char,
+    turn: Turn,
+    next: usize, // index of the next state
+}
 
-    /// Number of steps which cause a node to become infected.
-    new_infections: u32,
+#[derive(Debug)]
+pub struct Rules {
+    states: Vec<Rule>,
+    infected: usize, // index of the state whose nodes are infected
 }
 
-impl From<&str> for Virus {
-    fn from(value: &str) -> Self {
-        // parse the input into a two-dimensional grid indicating the infected cells
-        let grid: Vec<Vec<_>> = value.lines().fold(Vec::new(), |mut grid, line| {
-            grid.push(line.chars().map(|ch| ch == '#').collect());
-            grid
-        });
+/// Errors found in the rule table or in the grid.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RuleError {
+    Rule(String),  // line which could not be parsed
+    Turn(String),  // unknown turn
+    State(String), // state which is not defined
+    Symbol(char),  // symbol of no state
+    States(usize), // more states than a grid cell can hold
+}
 
-        // The grid is a square with sides of odd lengths, so the offset
-        // is the value of the x and y coorinates of its center.
-        let offset = grid.len() as i32 / 2;
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Rule(line) => write!(f, "invalid rule: {}", line),
+            RuleError::Turn(turn) => write!(f, "invalid turn: {}", turn),
+            RuleError::State(name) => write!(f, "unknown state: {}", name),
+            RuleError::Symbol(symbol) => write!(f, "unknown symbol: {}", symbol),
+            RuleError::States(count) => {
+                write!(f, "{} states, at most {} are supported", count, MAX_STATES)
+            }
+        }
+    }
+}
 
-        let mut nodes = HashMap::new();
+impl TryFrom<&str> for Rules {
+    type Error = RuleError;
 
-        // iterate over the grid and insert the coordinates of infected nodes into the set
-        for row in 0..grid.len() {
-            for col in (0..grid.len()).filter(|&col| grid[row][col]) {
-                // compute the coordinates of the infected node relative to the grid center
-                let x = col as i32 - offset;
-                let y = offset - row as i32;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let lines: Vec<Vec<&str>> = value
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
 
-                nodes.insert(Complex { x, y }, Status::Infected);
-            }
+        if lines.len() > MAX_STATES {
+            return Err(RuleError::States(lines.len()));
         }
 
-        Self {
-            nodes,
-            pos: Complex { x: 0, y: 0 },
-            dir: Complex { x: 0, y: 1 },
-            new_infections: 0,
-        }
+        let state = |name: &str| {
+            lines
+                .iter()
+                .position(|line| line.first() == Some(&name))
+                .ok_or_else(|| RuleError::State(name.to_string()))
+        };
+
+        let states = lines
+            .iter()
+            .map(|line| {
+                let error = || RuleError::Rule(line.join(" "));
+
+                let [_, symbol, turn, next] = line[..] else {
+                    return Err(error());
+                };
+
+                let mut chars = symbol.chars();
+
+                let (Some(symbol), None) = (chars.next(), chars.next()) else {
+                    return Err(error());
+                };
+
+                let turn = match turn {
+                    "left" => Turn::Left,
+                    "right" => Turn::Right,
+                    "straight" => Turn::Straight,
+                    "reverse" => Turn::Reverse,
+                    _ => return Err(RuleError::Turn(turn.to_string())),
+                };
+
+                Ok(Rule {
+                    symbol,
+                    turn,
+                    next: state(next)?,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            states,
+            infected: state("infected")?,
+        })
     }
 }
 
-impl Virus {
-    /// Works one step following the rules of Part One.
-    fn work_v1(&mut self) {
-        // recover and clear the status of the current node
-        // in Part One, only the Infected status is possible
-        let is_infected = self.nodes.remove(&self.pos).is_some();
+impl Rules {
+    /// Returns the state drawn with the `symbol`.
+    fn state(&self, symbol: char) -> Result<usize, RuleError> {
+        self.states
+            .iter()
+            .position(|rule| rule.symbol == symbol)
+            .ok_or(RuleError::Symbol(symbol))
+    }
+}
 
-        self.dir *= match is_infected {
-            true => Complex { x: 0, y: -1 },
-            false => Complex { x: 0, y: 1 },
-        };
+/// Dense grid of node states, which grows as the virus moves away.
+/// The nodes outside of the grid are clean (state 0).
+/// The rules have at most `MAX_STATES` states, so that each one fits in a cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    cells: Vec<u8>,
+    min: Complex, // coordinates of the first cell
+    width: i32,
+    height: i32,
+}
 
-        if !is_infected {
-            self.nodes.insert(self.pos.clone(), Status::Infected);
-            self.new_infections += 1;
+impl Grid {
+    fn new(min: Complex, width: i32, height: i32) -> Self {
+        Self {
+            cells: vec![0; (width * height) as usize],
+            min,
+            width,
+            height,
         }
+    }
 
-        self.pos += self.dir.clone();
+    fn index(&self, pos: &Complex) -> Option<usize> {
+        let (col, row) = (pos.x - self.min.x, pos.y - self.min.y);
+
+        match (0..self.width).contains(&col) && (0..self.height).contains(&row) {
+            true => Some((row * self.width + col) as usize),
+            false => None,
+        }
     }
 
-    /// Works one step following the rules of Part Two.
-    fn work_v2(&mut self) {
-        // recover and clear the status of the current node
-        let status = self.nodes.remove(&self.pos).unwrap_or(Status::Clean);
+    fn get(&self, pos: &Complex) -> usize {
+        self.index(pos).map_or(0, |idx| self.cells[idx] as usize)
+    }
 
-        // update the direction depending on the status of the current node
-        self.dir *= match status {
-            Status::Clean => Complex { x: 0, y: 1 },
-            Status::Weakened => Complex { x: 1, y: 0 },
-            Status::Infected => Complex { x: 0, y: -1 },
-            Status::Flagged => Complex { x: -1, y: 0 },
+    fn set(&mut self, pos: &Complex, state: usize) {
+        let idx = match self.index(pos) {
+            Some(idx) => idx,
+            None => {
+                self.grow(pos);
+                self.index(pos).unwrap()
+            }
         };
 
-        let new_status = status.update();
+        self.cells[idx] = state as u8;
+    }
 
-        if new_status == Status::Infected {
-            self.new_infections += 1;
+    /// Grows the grid on all sides until it contains `pos`,
+    /// doubling its size each time so that growing is rare.
+    fn grow(&mut self, pos: &Complex) {
+        let (mut min, mut width, mut height) = (self.min, self.width, self.height);
+
+        let contains = |min: Complex, width: i32, height: i32| {
+            (min.x..min.x + width).contains(&pos.x) && (min.y..min.y + height).contains(&pos.y)
+        };
+
+        while !contains(min, width, height) {
+            let pad = width.max(height) / 2 + 1;
+
+            min = Complex {
+                x: min.x - pad,
+                y: min.y - pad,
+            };
+            width += 2 * pad;
+            height += 2 * pad;
         }
 
-        // insert the node in the map if it is not clean
-        if new_status != Status::Clean {
-            self.nodes.insert(self.pos.clone(), new_status);
+        let mut grid = Grid::new(min, width, height);
+
+        // copy the rows of the old grid
+        for row in 0..self.height {
+            let start = grid
+                .index(&Complex {
+                    x: self.min.x,
+                    y: self.min.y + row,
+                })
+                .unwrap();
+            let old = (row * self.width) as usize;
+
+            grid.cells[start..start + self.width as usize]
+                .copy_from_slice(&self.cells[old..old + self.width as usize]);
         }
 
-        // take a step in the current direction
-        self.pos += self.dir.clone();
+        *self = grid;
     }
 }
 
@@ -144,25 +263,176 @@ fn main() {
 
     // --- Part One --- //
 
-    let mut virus = Virus::from(&input[..]);
+    let rules = Rules::try_from(RULES_V1).unwrap_or_else(|err| panic!("{}", err));
+    let mut virus = Virus::new(&input, &rules).unwrap_or_else(|err| panic!("{}", err));
 
-    for _ in 0..10_000 {
-        virus.work_v1();
-    }
-
-    let part_one = virus.new_infections;
+    let part_one = virus.by_ref().take(10_000).filter(|b| b.infection).count();
 
     println!("Part One: {}", part_one);
 
+    // the final grid is drawn when the `--snapshot` flag is given
+    if std::env::args().any(|arg| arg == "--snapshot") {
+        println!("{}", virus.snapshot());
+    }
+
     // --- Part Two --- //
 
-    let mut virus = Virus::from(&input[..]);
+    let rules = Rules::try_from(RULES_V2).unwrap_or_else(|err| panic!("{}", err));
+    let mut virus = Virus::new(&input, &rules).unwrap_or_else(|err| panic!("{}", err));
+
+    let (part_two, growth) = virus
+        .by_ref()
+        .take(10_000_000)
+        .fold((0, 0), |(infections, growth), b| {
+            (infections + b.infection as u32, growth + b.grew as u32)
+        });
+
+    println!("Part Two: {}", part_two);
+
+    let (min, max) = virus.state.bbox;
+
+    println!(
+        "Visited region: {}x{} nodes, grown in {} bursts",
+        max.x - min.x + 1,
+        max.y - min.y + 1,
+        growth
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "..#\n#..\n...";
+
+    #[test]
+    fn part_one() {
+        let rules = Rules::try_from(RULES_V1).unwrap();
+
+        let mut virus = Virus::new(EXAMPLE, &rules).unwrap();
+        assert_eq!(5, virus.by_ref().take(7).filter(|b| b.infection).count());
 
-    for _ in 0..10_000_000 {
-        virus.work_v2();
+        let mut virus = Virus::new(EXAMPLE, &rules).unwrap();
+        assert_eq!(41, virus.by_ref().take(70).filter(|b| b.infection).count());
+
+        let virus = Virus::new(EXAMPLE, &rules).unwrap();
+        assert_eq!(5587, virus.take(10_000).filter(|b| b.infection).count());
     }
 
-    let part_two = virus.new_infections;
+    #[test]
+    fn part_two() {
+        let rules = Rules::try_from(RULES_V2).unwrap();
 
-    println!("Part Two: {}", part_two);
+        let virus = Virus::new(EXAMPLE, &rules).unwrap();
+        assert_eq!(26, virus.take(100).filter(|b| b.infection).count());
+    }
+
+    #[test]
+    fn snapshot() {
+        let rules = Rules::try_from(RULES_V1).unwrap();
+        let mut virus = Virus::new(EXAMPLE, &rules).unwrap();
+
+        virus.by_ref().take(7).for_each(drop);
+
+        // the virus stands on the clean node of the top row
+        let expected = " #  . [.] # \n #  #  #  . \n";
+        assert_eq!(expected, virus.snapshot());
+    }
+
+    #[test]
+    fn checkpoint() {
+        let rules = Rules::try_from(RULES_V2).unwrap();
+        let mut virus = Virus::new(EXAMPLE, &rules).unwrap();
+
+        virus.by_ref().take(50).for_each(drop);
+        let checkpoint = virus.checkpoint();
+        let snapshot = virus.snapshot();
+
+        // the grid grows while the virus moves on
+        let infections = virus.by_ref().take(1000).filter(|b| b.infection).count();
+        let end = virus.checkpoint();
+        assert_ne!(checkpoint, end);
+
+        virus.restore(checkpoint);
+        assert_eq!(snapshot, virus.snapshot());
+
+        let replayed = virus.by_ref().take(1000).filter(|b| b.infection).count();
+        assert_eq!(infections, replayed);
+        assert_eq!(end, virus.checkpoint());
+    }
+
+    #[test]
+    fn grow() {
+        let mut grid = Grid::new(Complex { x: 0, y: 0 }, 2, 1);
+        grid.set(&Complex { x: 1, y: 0 }, 1);
+
+        grid.set(&Complex { x: 5, y: -4 }, 2);
+
+        assert!(grid.index(&Complex { x: 5, y: -4 }).is_some());
+        assert_eq!(1, grid.get(&Complex { x: 1, y: 0 }));
+        assert_eq!(2, grid.get(&Complex { x: 5, y: -4 }));
+        assert_eq!(
+            3,
+            grid.cells.iter().map(|&cell| cell as usize).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let rules = Rules::try_from(RULES_V1).unwrap();
+        assert_eq!(
+            RuleError::Symbol('W'),
+            Virus::new("..#\n#W.\n...", &rules).err().unwrap()
+        );
+
+        let errors = [
+            ("clean . left", RuleError::Rule("clean . left".to_string())),
+            (
+                "clean .. left clean",
+                RuleError::Rule("clean .. left clean".to_string()),
+            ),
+            ("clean . up infected", RuleError::Turn("up".to_string())),
+            (
+                "clean . left infected",
+                RuleError::State("infected".to_string()),
+            ),
+            (
+                "infected # left clean",
+                RuleError::State("clean".to_string()),
+            ),
+        ];
+
+        for (table, error) in errors {
+            assert_eq!(error, Rules::try_from(table).err().unwrap());
+        }
+
+        // a state per byte value fits, one more does not
+        let table = |count: usize| {
+            (0..count)
+                .map(|idx| match idx {
+                    0 => "infected . left infected".to_string(),
+                    _ => format!(
+                        "s{} {} left infected",
+                        idx,
+                        char::from_u32(idx as u32 + 256).unwrap()
+                    ),
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        assert_eq!(
+            MAX_STATES,
+            Rules::try_from(table(MAX_STATES).as_str())
+                .unwrap()
+                .states
+                .len()
+        );
+        assert_eq!(
+            RuleError::States(MAX_STATES + 1),
+            Rules::try_from(table(MAX_STATES + 1).as_str())
+                .err()
+                .unwrap()
+        );
+    }
 }
@@ -0,0 +1,167 @@
+use crate::{Complex, Grid, RuleError, Rules};
+
+/// Statistics of a single burst.
+#[derive(Clone, Copy, Debug)]
+pub struct Burst {
+    pub infection: bool, // whether the burst infected the node
+    pub grew: bool,      // whether the burst grew the bounding box of the visited region
+}
+
+/// State of the virus, which can be restored later.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub(crate) grid: Grid,
+    pub(crate) pos: Complex,
+    pub(crate) dir: Complex,
+    pub(crate) bursts: u64,
+    pub(crate) infections: u64,
+    pub(crate) bbox: (Complex, Complex),
+}
+
+pub struct Virus<'a> {
+    rules: &'a Rules,
+    pub(crate) state: Checkpoint,
+}
+
+impl<'a> Virus<'a> {
+    /// Creates a virus at the center of the grid given in the input, facing up.
+    pub fn new(input: &str, rules: &'a Rules) -> Result<Self, RuleError> {
+        let lines: Vec<&str> = input.lines().collect();
+
+        // The grid is a square with sides of odd lengths, so the offset
+        // is the value of the x and y coorinates of its center.
+        let size = lines.len() as i32;
+        let offset = size / 2;
+
+        let mut grid = Grid::new(
+            Complex {
+                x: -offset,
+                y: -offset,
+            },
+            size,
+            size,
+        );
+
+        for (row, line) in lines.iter().enumerate() {
+            for (col, ch) in line.chars().enumerate() {
+                // compute the coordinates of the node relative to the grid center
+                let x = col as i32 - offset;
+                let y = offset - row as i32;
+
+                grid.set(&Complex { x, y }, rules.state(ch)?);
+            }
+        }
+
+        let bbox = (
+            grid.min,
+            Complex {
+                x: grid.min.x + size - 1,
+                y: grid.min.y + size - 1,
+            },
+        );
+
+        Ok(Self {
+            rules,
+            state: Checkpoint {
+                grid,
+                pos: Complex { x: 0, y: 0 },
+                dir: Complex { x: 0, y: 1 },
+                bursts: 0,
+                infections: 0,
+                bbox,
+            },
+        })
+    }
+
+    /// Works a single burst: turn, update the current node and move forward.
+    pub fn burst(&mut self) -> Burst {
+        let state = &mut self.state;
+        let pos = state.pos;
+
+        let from = state.grid.get(&pos);
+        let rule = &self.rules.states[from];
+
+        state.dir *= rule.turn.factor();
+        state.grid.set(&pos, rule.next);
+
+        let infection = rule.next == self.rules.infected;
+
+        if infection {
+            state.infections += 1;
+        }
+
+        let (min, max) = state.bbox;
+        let bbox = (
+            Complex {
+                x: min.x.min(pos.x),
+                y: min.y.min(pos.y),
+            },
+            Complex {
+                x: max.x.max(pos.x),
+                y: max.y.max(pos.y),
+            },
+        );
+        let grew = bbox != state.bbox;
+
+        state.bbox = bbox;
+        state.pos += state.dir;
+        state.bursts += 1;
+
+        Burst { infection, grew }
+    }
+
+    /// Saves the state of the virus and of the grid.
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.state.clone()
+    }
+
+    /// Restores a state saved with [`Virus::checkpoint`].
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.state = checkpoint;
+    }
+
+    /// Draws the region containing the nodes which are not clean,
+    /// with the current node in brackets.
+    pub fn snapshot(&self) -> String {
+        let state = &self.state;
+        let grid = &state.grid;
+
+        // bounding box of the nodes which are not clean, and of the current node
+        let (mut min, mut max) = (state.pos, state.pos);
+
+        for (idx, _) in grid.cells.iter().enumerate().filter(|(_, &cell)| cell != 0) {
+            let x = grid.min.x + idx as i32 % grid.width;
+            let y = grid.min.y + idx as i32 / grid.width;
+
+            (min.x, min.y) = (min.x.min(x), min.y.min(y));
+            (max.x, max.y) = (max.x.max(x), max.y.max(y));
+        }
+
+        let mut drawing = String::new();
+
+        for y in (min.y..=max.y).rev() {
+            for x in min.x..=max.x {
+                let pos = Complex { x, y };
+                let symbol = self.rules.states[state.grid.get(&pos)].symbol;
+
+                match pos == state.pos {
+                    true => drawing.push_str(&format!("[{}]", symbol)),
+                    false => drawing.push_str(&format!(" {} ", symbol)),
+                }
+            }
+
+            drawing.push('\n');
+        }
+
+        drawing
+    }
+}
+
+/// Stream of the statistics of each burst.
+impl Iterator for Virus<'_> {
+    type Item = Burst;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.burst())
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::Cmd;
+
+// With a = 1, the program counts the composite numbers among b, b + step, ..., c,
+// testing every product d * e with 2 <= d, e < b (see the pseudocode printed by the decompiler).
+// The inputs only differ in the constants, so the program is matched against a template
+// in which the constants are replaced by named placeholders.
+
+/// Largest end of the range which is evaluated, as the sieve takes a byte per number.
+const MAX_END: i64 = 100_000_000;
+
+const TEMPLATE: [&str; 32] = [
+    "set b #seed",
+    "set c b",
+    "jnz a 2",
+    "jnz 1 5",
+    "mul b #scale",
+    "sub b #shift",
+    "set c b",
+    "sub c #span",
+    "set f 1",
+    "set d 2",
+    "set e 2",
+    "set g d",
+    "mul g e",
+    "sub g b",
+    "jnz g 2",
+    "set f 0",
+    "sub e -1",
+    "set g e",
+    "sub g b",
+    "jnz g -8",
+    "sub d -1",
+    "set g d",
+    "sub g b",
+    "jnz g -13",
+    "jnz f 2",
+    "sub h -1",
+    "set g b",
+    "sub g c",
+    "jnz g 2",
+    "jnz 1 3",
+    "sub b #step",
+    "jnz 1 -23",
+];
+
+/// Error returned when a program does not match the template.
+#[derive(Debug)]
+pub enum TemplateError {
+    Length(usize),
+    Mismatch {
+        index: usize,
+        expected: &'static str,
+        found: String,
+    },
+    Parameters(String), // the constants make the program loop forever, or overflow
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Length(len) => {
+                write!(f, "expected {} commands, found {}", TEMPLATE.len(), len)
+            }
+            TemplateError::Mismatch {
+                index,
+                expected,
+                found,
+            } => write!(
+                f,
+                "command {} does not match the template: expected '{}', found '{}'",
+                index, expected, found
+            ),
+            TemplateError::Parameters(msg) => write!(f, "unsupported constants: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// The computation done by the program: count the composite numbers
+/// among `start`, `start + step`, ..., `end`.
+#[derive(Debug, Clone, Copy)]
+pub struct CompositeCount {
+    pub start: u64,
+    pub end: u64,
+    pub step: u64,
+}
+
+impl CompositeCount {
+    /// Checks that the program matches the template and extracts the computation
+    /// it does when register a starts with the value `a`.
+    pub fn recognize(cmds: &[Cmd], a: i64) -> Result<Self, TemplateError> {
+        if cmds.len() != TEMPLATE.len() {
+            return Err(TemplateError::Length(cmds.len()));
+        }
+
+        let mut consts: HashMap<&str, i64> = HashMap::new();
+
+        for (index, (cmd, pattern)) in cmds.iter().zip(TEMPLATE).enumerate() {
+            let found = cmd.to_string();
+
+            let mismatch = || TemplateError::Mismatch {
+                index,
+                expected: pattern,
+                found: found.clone(),
+            };
+
+            let tokens: Vec<&str> = found.split_whitespace().collect();
+            let expected: Vec<&str> = pattern.split_whitespace().collect();
+
+            for (token, expected) in tokens.iter().zip(expected) {
+                match expected.strip_prefix('#') {
+                    Some(name) => {
+                        let int = token.parse().map_err(|_| mismatch())?;
+                        consts.insert(name, int);
+                    }
+                    None if *token == expected => {}
+                    None => return Err(mismatch()),
+                }
+            }
+        }
+
+        let overflow =
+            || TemplateError::Parameters(format!("the setup overflows with {:?}", consts));
+
+        // evaluate the setup, before the loop at index 8
+        let mut b = consts["seed"];
+        let mut c = b;
+
+        if a != 0 {
+            b = b
+                .checked_mul(consts["scale"])
+                .and_then(|b| b.checked_sub(consts["shift"]))
+                .ok_or_else(overflow)?;
+            c = b.checked_sub(consts["span"]).ok_or_else(overflow)?;
+        }
+
+        let step = consts["step"].checked_neg().ok_or_else(overflow)?;
+
+        // the loop stops when b is equal to c after a step
+        if b <= 2 {
+            return Err(TemplateError::Parameters(format!("b = {} is below 3", b)));
+        }
+
+        if step <= 0 || c < b || (c - b) % step != 0 {
+            return Err(TemplateError::Parameters(format!(
+                "b = {} never reaches c = {} by steps of {}",
+                b, c, step
+            )));
+        }
+
+        if c > MAX_END {
+            return Err(TemplateError::Parameters(format!(
+                "c = {} is above {}",
+                c, MAX_END
+            )));
+        }
+
+        Ok(Self {
+            start: b as u64,
+            end: c as u64,
+            step: step as u64,
+        })
+    }
+
+    /// Counts the composite numbers with a sieve of Eratosthenes up to `end`.
+    pub fn evaluate(&self) -> usize {
+        let end = self.end as usize;
+
+        let mut composite = vec![false; end + 1];
+
+        for n in (2..).take_while(|n| n * n <= end) {
+            if !composite[n] {
+                for k in (n * n..=end).step_by(n) {
+                    composite[k] = true;
+                }
+            }
+        }
+
+        (self.start as usize..=end)
+            .step_by(self.step as usize)
+            .filter(|&n| composite[n])
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the template with the given constants.
+    fn program(consts: [(&str, i64); 5]) -> Vec<Cmd> {
+        TEMPLATE
+            .iter()
+            .map(|pattern| {
+                let line = consts
+                    .iter()
+                    .fold(pattern.to_string(), |line, (name, int)| {
+                        line.replace(&format!("#{}", name), &int.to_string())
+                    });
+
+                Cmd::from(line.as_str())
+            })
+            .collect()
+    }
+
+    fn consts(seed: i64, scale: i64, shift: i64, span: i64) -> [(&'static str, i64); 5] {
+        [
+            ("seed", seed),
+            ("scale", scale),
+            ("shift", shift),
+            ("span", span),
+            ("step", -17),
+        ]
+    }
+
+    #[test]
+    fn recognize() {
+        let cmds = program(consts(81, 100, -100_000, -17_000));
+
+        let count = CompositeCount::recognize(&cmds, 1).unwrap();
+        assert_eq!((108_100, 125_100, 17), (count.start, count.end, count.step));
+
+        let count = CompositeCount::recognize(&cmds, 0).unwrap();
+        assert_eq!((81, 81), (count.start, count.end));
+        assert_eq!(1, count.evaluate());
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut cmds = program(consts(81, 100, -100_000, -17_000));
+        cmds[12] = Cmd::from("mul g d");
+
+        assert!(matches!(
+            CompositeCount::recognize(&cmds, 1),
+            Err(TemplateError::Mismatch { index: 12, .. })
+        ));
+        assert!(matches!(
+            CompositeCount::recognize(&cmds[1..], 1),
+            Err(TemplateError::Length(31))
+        ));
+    }
+
+    #[test]
+    fn parameters() {
+        let errors = [
+            consts(i64::MAX / 10, 100, 0, 0),    // b * scale overflows
+            consts(81, 100, i64::MIN, 0),        // b - shift overflows
+            consts(81, 100, -100_000, i64::MIN), // c = b - span overflows
+            consts(81, 100, -100_000, 17_000),   // c < b
+            consts(81, 100, -100_000, -17_001),  // c is not reached from b
+            consts(81, 100_000_000, 0, -17_000), // the range is too large to sieve
+        ];
+
+        for consts in errors {
+            let result = CompositeCount::recognize(&program(consts), 1);
+            assert!(
+                matches!(result, Err(TemplateError::Parameters(_))),
+                "{:?}",
+                consts
+            );
+        }
+
+        let mut consts = consts(81, 100, -100_000, -17_000);
+        consts[4] = ("step", i64::MIN);
+
+        let result = CompositeCount::recognize(&program(consts), 1);
+        assert!(matches!(result, Err(TemplateError::Parameters(_))));
+    }
+
+    #[test]
+    fn evaluate() {
+        let count = |start, end, step| CompositeCount { start, end, step }.evaluate();
+
+        assert_eq!(6, count(2, 13, 1)); // 4, 6, 8, 9, 10, 12
+        assert_eq!(5, count(10, 30, 5));
+        assert_eq!(0, count(3, 3, 1));
+
+        // compare with trial division
+        let is_composite = |n: u64| {
+            (2..n)
+                .take_while(|d| d * d <= n)
+                .any(|d| n.is_multiple_of(d))
+        };
+        let expected = (1000..=5000)
+            .step_by(17)
+            .filter(|&n| is_composite(n))
+            .count();
+
+        assert_eq!(expected, count(1000, 4995, 17));
+    }
+}
@@ -1,5 +1,13 @@
+// The composites module recognizes the program of part two and evaluates it.
+pub mod composites;
+
+use arithmetic::{Arithmetic, Checked, Fault, RuntimeError};
+use composites::CompositeCount;
+use decompiler::{Cond, Decompiler, Lift, Op};
+
+#[derive(Debug, Clone, Copy)]
 enum Arg {
-    Int(i32),
+    Int(i64),
     Reg(usize),
 }
 
@@ -12,6 +20,16 @@ impl From<&str> for Arg {
     }
 }
 
+impl std::fmt::Display for Arg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::Int(int) => write!(f, "{}", int),
+            Arg::Reg(reg) => write!(f, "{}", (b'a' + *reg as u8) as char),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
 enum CmdKind {
     Set,
     Sub,
@@ -31,7 +49,8 @@ impl From<&str> for CmdKind {
     }
 }
 
-struct Cmd {
+#[derive(Debug, Clone, Copy)]
+pub struct Cmd {
     kind: CmdKind,
     arg0: Arg,
     arg1: Arg,
@@ -49,65 +68,125 @@ impl From<&str> for Cmd {
     }
 }
 
-struct Coprocessor {
-    registers: [i32; 8],
-    offset: i32,
+impl std::fmt::Display for Cmd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            CmdKind::Set => "set",
+            CmdKind::Sub => "sub",
+            CmdKind::Mul => "mul",
+            CmdKind::Jnz => "jnz",
+        };
+
+        write!(f, "{} {} {}", kind, self.arg0, self.arg1)
+    }
+}
+
+impl Lift for Cmd {
+    fn lift(&self, idx: usize) -> Op {
+        let (x, y) = (self.arg0, self.arg1);
+
+        match (self.kind, x, y) {
+            (CmdKind::Jnz, Arg::Int(0), _) => Op::Nop,
+            (CmdKind::Jnz, Arg::Int(_), Arg::Int(jmp)) => Op::Jump(Cond::Always, idx as i64 + jmp),
+            (CmdKind::Jnz, _, Arg::Int(jmp)) => {
+                Op::Jump(Cond::NonZero(x.to_string()), idx as i64 + jmp)
+            }
+            (CmdKind::Jnz, _, _) => Op::Opaque(format!("jump {} if {} != 0", y, x)),
+            (_, Arg::Int(_), _) => Op::Nop, // writing to an integer does nothing
+            (CmdKind::Set, _, _) => Op::Stmt(format!("{} = {}", x, y)),
+            (CmdKind::Sub, _, Arg::Int(int)) if int < 0 => Op::Stmt(format!("{} += {}", x, -int)),
+            (CmdKind::Sub, _, _) => Op::Stmt(format!("{} -= {}", x, y)),
+            (CmdKind::Mul, _, _) => Op::Stmt(format!("{} *= {}", x, y)),
+        }
+    }
+}
+
+struct Coprocessor<A: Arithmetic> {
+    registers: [A::Word; 8],
+    offset: i64,
     mul_count: u32,
 }
 
-impl Coprocessor {
+impl<A: Arithmetic> Coprocessor<A> {
     fn new() -> Self {
         Self {
-            registers: [0; 8],
+            registers: core::array::from_fn(|_| A::zero()),
             offset: 0,
             mul_count: 0,
         }
     }
 
-    fn get(&self, arg: &Arg) -> i32 {
+    fn get(&self, arg: &Arg) -> Result<A::Word, Fault> {
         match *arg {
-            Arg::Int(int) => int,
-            Arg::Reg(reg) => self.registers[reg],
+            Arg::Int(int) => A::word(int),
+            Arg::Reg(reg) => Ok(self.registers[reg].clone()),
         }
     }
 
-    fn set(&mut self, arg0: &Arg, arg1: &Arg) {
+    fn set(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = *arg0 {
-            self.registers[reg] = self.get(arg1);
+            self.registers[reg] = self.get(arg1)?;
         }
+
+        Ok(())
     }
 
-    fn sub(&mut self, arg0: &Arg, arg1: &Arg) {
+    fn sub(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = *arg0 {
-            self.registers[reg] -= self.get(arg1);
+            self.registers[reg] = A::sub(&self.registers[reg], &self.get(arg1)?)?;
         }
+
+        Ok(())
     }
 
-    fn mul(&mut self, arg0: &Arg, arg1: &Arg) {
+    fn mul(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
         if let Arg::Reg(reg) = *arg0 {
-            self.registers[reg] *= self.get(arg1);
+            self.registers[reg] = A::mul(&self.registers[reg], &self.get(arg1)?)?;
             self.mul_count += 1;
         }
+
+        Ok(())
     }
 
-    fn jnz(&mut self, arg0: &Arg, arg1: &Arg) {
-        if self.get(arg0) != 0 {
-            self.offset = self.get(arg1);
+    fn jnz(&mut self, arg0: &Arg, arg1: &Arg) -> Result<(), Fault> {
+        if self.get(arg0)? != A::zero() {
+            self.offset = A::int(&self.get(arg1)?)?;
         }
+
+        Ok(())
     }
 
-    fn execute(&mut self, cmd: &Cmd) -> i32 {
+    fn execute(&mut self, cmd: &Cmd) -> Result<i64, Fault> {
         match cmd.kind {
-            CmdKind::Set => self.set(&cmd.arg0, &cmd.arg1),
-            CmdKind::Sub => self.sub(&cmd.arg0, &cmd.arg1),
-            CmdKind::Mul => self.mul(&cmd.arg0, &cmd.arg1),
-            CmdKind::Jnz => self.jnz(&cmd.arg0, &cmd.arg1),
+            CmdKind::Set => self.set(&cmd.arg0, &cmd.arg1)?,
+            CmdKind::Sub => self.sub(&cmd.arg0, &cmd.arg1)?,
+            CmdKind::Mul => self.mul(&cmd.arg0, &cmd.arg1)?,
+            CmdKind::Jnz => self.jnz(&cmd.arg0, &cmd.arg1)?,
         }
 
         let jump = if self.offset != 0 { self.offset } else { 1 };
         self.offset = 0;
 
-        jump
+        Ok(jump)
+    }
+
+    /// Runs the commands until the index goes out of bounds.
+    ///
+    /// Returns an error with the index of the command if an operation fails.
+    fn run(&mut self, cmds: &[Cmd]) -> Result<(), RuntimeError<Cmd>> {
+        let mut idx: i64 = 0;
+
+        while (0 <= idx) && (idx < cmds.len() as i64) {
+            let cmd = cmds[idx as usize];
+
+            idx += self.execute(&cmd).map_err(|fault| RuntimeError {
+                fault,
+                pc: idx as usize,
+                cmd,
+            })?;
+        }
+
+        Ok(())
     }
 }
 
@@ -118,13 +197,9 @@ fn main() {
 
     // --- Part One --- //
 
-    let mut proc = Coprocessor::new();
+    let mut proc: Coprocessor<Checked<i32>> = Coprocessor::new();
 
-    let mut idx: i32 = 0;
-
-    while (0 <= idx) && (idx < cmds.len() as i32) {
-        idx += proc.execute(&cmds[idx as usize]);
-    }
+    proc.run(&cmds).unwrap_or_else(|err| panic!("{}", err));
 
     let part_one = proc.mul_count;
 
@@ -132,52 +207,13 @@ fn main() {
 
     // --- Part Two --- //
 
-    // We read the assembly code and implement the resulting program,
-    // adding some optimizations.
-
-    // get the value that gets put in register b at the start
-    let x = match cmds[0].arg1 {
-        Arg::Int(int) => int as u64,
-        Arg::Reg(_) => unreachable!(),
-    };
-
-    // initialize registers b and c
-    let mut b = 100_000 + 100 * x;
-    let c = b + 17_000;
-
-    // The program counts the composite numbers of the form n = b + 17k,
-    // with b <= n <= c.
-
-    let mut h = 0;
-
-    while b <= c {
-        let mut f = 1;
-
-        // Check whether b is prime.
-        // Here, we optimize the code, as the original code uses two loops:
-        // for d in 2..b {
-        //     for e in 2..b {
-        //         // check if d * e == b
-        //     }
-        // }
-        for n in 2..b {
-            if b % n == 0 {
-                f = 0;
-                break;
-            }
-        }
-
-        if f == 0 {
-            h += 1;
-        }
-
-        b += 17;
-    }
+    // The program is too slow to run as is: recognize what it computes instead.
 
-    // We could optimize the colde further, but there is no point.
-    // Instead, we keep it as close to the input as possible.
+    println!("{}", Decompiler::new(&cmds).pseudocode());
 
-    let part_two = h;
+    let part_two = CompositeCount::recognize(&cmds, 1)
+        .unwrap_or_else(|err| panic!("{}", err))
+        .evaluate();
 
     println!("Part Two: {}", part_two);
 }
@@ -1,82 +1,124 @@
-struct Component {
+use std::collections::HashMap;
+use std::fmt;
+
+// The objective module contains what the search computes: best bridges or their number.
+pub mod objective;
+
+use objective::{Best, Objective, Score};
+
+pub struct Component {
     id: usize,
     ports: [u32; 2],
 }
 
 impl Component {
-    fn is_compatible(&self, port: u32) -> bool {
-        self.ports[0] == port || self.ports[1] == port
-    }
-
     fn strength(&self) -> u32 {
         self.ports[0] + self.ports[1]
     }
+
+    /// Returns the value of the port opposite to `port`.
+    fn other_port(&self, port: u32) -> u32 {
+        if self.ports[0] == port {
+            self.ports[1]
+        } else {
+            self.ports[0]
+        }
+    }
+}
+
+// The used components are stored in a bitmask, where the component with id k
+// corresponds to the k-th bit, so that the state of a partial bridge is the pair
+// (used components, exposed port). The extensions of a bridge only depend on this
+// state and not on the order of the components, so the value of the objective over
+// the extensions of each state is memoized.
+
+type Mask = u128;
+
+/// Error raised when there are more components than bits in the mask.
+#[derive(Debug, PartialEq, Eq)]
+struct TooManyComponents(usize);
+
+impl fmt::Display for TooManyComponents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} components, at most {} are supported",
+            self.0,
+            Mask::BITS
+        )
+    }
 }
 
-#[derive(Clone)]
-struct Bridge {
-    /// List of used components, identified by their component id.
-    used: Vec<bool>,
+impl std::error::Error for TooManyComponents {}
 
-    /// Value of the exposed port of the bridge.
-    port: u32,
+/// Depth-first search over the bridges that can be built from a list of components.
+struct Search<'a> {
+    components: &'a [Component],
+    by_port: HashMap<u32, Vec<usize>>, // ids of the components with a given port value
 }
 
-impl Bridge {
-    fn new(size: usize) -> Self {
-        let used = vec![false; size];
-        let port = 0;
+impl<'a> Search<'a> {
+    fn new(components: &'a [Component]) -> Result<Self, TooManyComponents> {
+        if components.len() > Mask::BITS as usize {
+            return Err(TooManyComponents(components.len()));
+        }
 
-        Self { used, port }
-    }
+        let mut by_port: HashMap<u32, Vec<usize>> = HashMap::new();
+
+        for comp in components {
+            by_port.entry(comp.ports[0]).or_default().push(comp.id);
 
-    /// Computes the total strength of the bridge.
-    ///
-    /// The strength of a bridge is the sum of the strength
-    /// of its components.
-    fn strength(&self, components: &[Component]) -> u32 {
-        (0..self.used.len())
-            .filter_map(|idx| match self.used[idx] {
-                true => Some(components[idx].strength()),
-                false => None,
-            })
-            .sum()
+            if comp.ports[1] != comp.ports[0] {
+                by_port.entry(comp.ports[1]).or_default().push(comp.id);
+            }
+        }
+
+        Ok(Self {
+            components,
+            by_port,
+        })
     }
 
-    /// Computes the length of the bridge.
-    ///
-    /// The length is the number of components used in the bridge.
-    fn len(&self) -> usize {
-        self.used.iter().filter(|&used| *used).count()
+    /// Lists the available components with a port matching `port`.
+    fn compatible(&self, used: Mask, port: u32) -> impl Iterator<Item = &Component> + '_ {
+        self.by_port
+            .get(&port)
+            .into_iter()
+            .flatten()
+            .filter(move |&&id| used & (1 << id) == 0)
+            .map(|&id| &self.components[id])
     }
 
-    /// Checks whether a component can be added to the end of the bridge.
-    ///
-    /// This is the case if the component has at least one port with
-    /// value matching the value of the bridge's exposed port, and if the
-    /// component is available (i.e. not yet in use in the bridge).
-    fn is_compatible(&self, component: &Component) -> bool {
-        component.is_compatible(self.port) && !self.used[component.id]
+    /// Computes the value of the objective over all the bridges.
+    fn run<O: Objective>(&self, objective: &O) -> O::Value {
+        self.extensions(objective, 0, 0, &mut HashMap::new())
     }
 
-    /// Extends the bridge by adding a component.
-    ///
-    /// Returns a new bridge with the component added.
-    fn extend(&self, component: &Component) -> Self {
-        // TODO remove? we check before extending
-        assert!(self.is_compatible(component));
+    /// Computes the value of the objective over the extensions of the bridge
+    /// in state (`used`, `port`).
+    fn extensions<O: Objective>(
+        &self,
+        objective: &O,
+        used: Mask,
+        port: u32,
+        memo: &mut HashMap<(Mask, u32), O::Value>,
+    ) -> O::Value {
+        if let Some(value) = memo.get(&(used, port)) {
+            return value.clone();
+        }
 
-        let mut bridge = self.clone();
+        let mut value = objective.empty();
 
-        bridge.used[component.id] = true;
+        for comp in self.compatible(used, port) {
+            let next = (used | (1 << comp.id), comp.other_port(port));
+            let rest = self.extensions(objective, next.0, next.1, memo);
 
-        bridge.port = if self.port == component.ports[0] {
-            component.ports[1]
-        } else {
-            component.ports[0]
-        };
+            value = objective.union(value, objective.prepend(comp, &rest));
+        }
+
+        memo.insert((used, port), value.clone());
 
-        bridge
+        value
     }
 }
 
@@ -85,7 +127,34 @@ fn main() {
 
     // Create the list of components.
     // The component ids are equal to the order in which the component appears in the input list.
-    let components = input
+    let components = parse_input(&input);
+
+    let search = Search::new(&components).unwrap_or_else(|err| panic!("{}", err));
+
+    // --- Part One --- //
+
+    let part_one = search.run(&Best(Score::Strongest)).strength;
+
+    println!("Part One: {}", part_one);
+
+    // --- Part Two --- //
+
+    let bridge = search.run(&Best(Score::LongestThenStrongest));
+    let part_two = bridge.strength;
+
+    println!("Part Two: {}", part_two);
+
+    let ports: Vec<String> = bridge
+        .components()
+        .iter()
+        .map(|&id| format!("{}/{}", components[id].ports[0], components[id].ports[1]))
+        .collect();
+
+    println!("Longest bridge: {}", ports.join("--"));
+}
+
+fn parse_input(input: &str) -> Vec<Component> {
+    input
         .lines()
         .enumerate()
         .fold(Vec::new(), |mut vec, (id, line)| {
@@ -95,55 +164,78 @@ fn main() {
             vec.push(Component { id, ports });
 
             vec
-        });
+        })
+}
 
-    // store the properties of the completed bridges
-    let mut strength_values = Vec::new();
-    let mut length_values = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use objective::{Count, Top};
 
-    // Build all possible bridges using depth-first search.
-    // Start with a single with an empty bridge with exposed port 0.
-    let mut stack: Vec<Bridge> = Vec::from([Bridge::new(components.len())]);
+    const EXAMPLE: &str = "0/2\n2/2\n2/3\n3/4\n3/5\n0/1\n10/1\n9/10";
 
-    while let Some(bridge) = stack.pop() {
-        // get a list of available compatible components to extend the bridge
-        let compat: Vec<_> = components
-            .iter()
-            .filter(|comp| bridge.is_compatible(comp))
-            .collect();
+    #[test]
+    fn best() {
+        let components = parse_input(EXAMPLE);
+        let search = Search::new(&components).unwrap();
 
-        // if no component is available, the bridge is complete
-        if compat.is_empty() {
-            strength_values.push(bridge.strength(&components));
-            length_values.push(bridge.len());
-        }
+        let bridge = search.run(&Best(Score::Strongest));
+        assert_eq!((31, vec![5, 6, 7]), (bridge.strength, bridge.components()));
 
-        // add every possible extension of the bridge to the stack
-        for comp in compat {
-            let new_bridge = bridge.extend(comp);
-            stack.push(new_bridge);
-        }
+        let bridge = search.run(&Best(Score::LongestThenStrongest));
+        assert_eq!(
+            (19, vec![0, 1, 2, 4]),
+            (bridge.strength, bridge.components())
+        );
     }
 
-    // --- Part One --- //
+    #[test]
+    fn count() {
+        let components = parse_input(EXAMPLE);
 
-    let part_one = *strength_values.iter().max().unwrap();
+        assert_eq!(11, Search::new(&components).unwrap().run(&Count));
+        assert_eq!(0, Search::new(&parse_input("1/2")).unwrap().run(&Count));
+    }
 
-    println!("Part One: {}", part_one);
+    #[test]
+    fn top() {
+        let components = parse_input(EXAMPLE);
+        let search = Search::new(&components).unwrap();
 
-    // --- Part Two --- //
+        let top: Vec<(u32, Vec<usize>)> = search
+            .run(&Top(3, Score::Strongest))
+            .iter()
+            .map(|bridge| (bridge.strength, bridge.components()))
+            .collect();
 
-    let max_length = length_values.iter().max().unwrap();
+        let expected = vec![
+            (31, vec![5, 6, 7]),
+            (19, vec![0, 1, 2, 4]),
+            (18, vec![0, 1, 2, 3]),
+        ];
+        assert_eq!(expected, top);
+
+        // asking for more bridges than there are gives them all
+        assert_eq!(11, search.run(&Top(20, Score::Strongest)).len());
+
+        let longest = search.run(&Top(2, Score::LongestThenStrongest));
+        assert_eq!(
+            vec![19, 18],
+            longest.iter().map(|b| b.strength).collect::<Vec<_>>()
+        );
+    }
 
-    let part_two = *strength_values
-        .iter()
-        .enumerate()
-        .filter_map(|(idx, strength)| match length_values[idx] == *max_length {
-            true => Some(strength),
-            false => None,
-        })
-        .max()
-        .unwrap();
+    #[test]
+    fn too_many_components() {
+        let input: Vec<String> = (0..=Mask::BITS)
+            .map(|i| format!("{}/{}", i, i + 1))
+            .collect();
+        let components = parse_input(&input.join("\n"));
 
-    println!("Part Two: {}", part_two);
+        assert!(Search::new(&components[..128]).is_ok());
+        assert_eq!(
+            TooManyComponents(129),
+            Search::new(&components).err().unwrap()
+        );
+    }
 }
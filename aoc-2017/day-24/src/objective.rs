@@ -0,0 +1,162 @@
+use std::cmp::Reverse;
+use std::rc::Rc;
+
+use crate::Component;
+
+/// What makes a bridge better than another.
+#[derive(Clone, Copy)]
+pub enum Score {
+    Strongest,
+    LongestThenStrongest,
+}
+
+impl Score {
+    /// Computes the score of a bridge, where a higher score is better.
+    pub fn of(&self, bridge: &Bridge) -> (u32, u32) {
+        match self {
+            Score::Strongest => (bridge.strength, 0),
+            Score::LongestThenStrongest => (bridge.len, bridge.strength),
+        }
+    }
+}
+
+/// Link of the list of components of a bridge.
+#[derive(Debug)]
+struct Link {
+    id: usize,
+    next: Option<Rc<Link>>,
+}
+
+/// A bridge given by its sequence of components, starting from the zero-pin port.
+///
+/// The bridges extending the same partial bridge end with the same components,
+/// so the components are a linked list whose tails are shared.
+#[derive(Debug, Clone, Default)]
+pub struct Bridge {
+    head: Option<Rc<Link>>,
+    pub strength: u32,
+    pub len: u32,
+}
+
+impl Bridge {
+    /// Returns the bridge starting with `comp`, followed by the components of `self`.
+    pub fn prepend(&self, comp: &Component) -> Bridge {
+        Bridge {
+            head: Some(Rc::new(Link {
+                id: comp.id,
+                next: self.head.clone(),
+            })),
+            strength: self.strength + comp.strength(),
+            len: self.len + 1,
+        }
+    }
+
+    /// Returns the ids of the components, from the zero-pin port.
+    pub fn components(&self) -> Vec<usize> {
+        let mut components = Vec::with_capacity(self.len as usize);
+        let mut link = &self.head;
+
+        while let Some(node) = link {
+            components.push(node.id);
+            link = &node.next;
+        }
+
+        components
+    }
+}
+
+/// Value computed by the search over a set of bridges, e.g. the best one or their number.
+///
+/// The value of the bridges extending a state is the union of the values of the bridges
+/// starting with each compatible component, so the objective only has to say how a
+/// component is put in front of a set of bridges, and how two sets are put together.
+pub trait Objective {
+    type Value: Clone;
+
+    /// Value of the set containing only the empty bridge, for the states which cannot be
+    /// extended. The objectives over the non-empty bridges leave the empty bridge out.
+    fn empty(&self) -> Self::Value;
+
+    /// Value of the bridges made of `comp` followed by a bridge of the set `rest`.
+    fn prepend(&self, comp: &Component, rest: &Self::Value) -> Self::Value;
+
+    /// Value of the union of two sets of bridges, which have no bridge in common
+    /// except the empty one.
+    fn union(&self, a: Self::Value, b: Self::Value) -> Self::Value;
+}
+
+/// Finds the best bridge for the score.
+pub struct Best(pub Score);
+
+impl Objective for Best {
+    type Value = Bridge;
+
+    fn empty(&self) -> Bridge {
+        Bridge::default()
+    }
+
+    fn prepend(&self, comp: &Component, rest: &Bridge) -> Bridge {
+        rest.prepend(comp)
+    }
+
+    fn union(&self, a: Bridge, b: Bridge) -> Bridge {
+        match self.0.of(&b) > self.0.of(&a) {
+            true => b,
+            false => a,
+        }
+    }
+}
+
+/// Counts the (non-empty) bridges.
+pub struct Count;
+
+impl Objective for Count {
+    type Value = u64;
+
+    fn empty(&self) -> u64 {
+        0
+    }
+
+    fn prepend(&self, _: &Component, rest: &u64) -> u64 {
+        1 + rest // the component alone, or followed by a bridge of the set
+    }
+
+    fn union(&self, a: u64, b: u64) -> u64 {
+        a + b
+    }
+}
+
+/// Finds the `k` best (non-empty) bridges for the score, best first.
+pub struct Top(pub usize, pub Score);
+
+impl Top {
+    fn truncate(&self, mut bridges: Vec<Bridge>) -> Vec<Bridge> {
+        // stable sort: the ties stay in the order in which they were found
+        bridges.sort_by_key(|bridge| Reverse(self.1.of(bridge)));
+        bridges.truncate(self.0);
+        bridges
+    }
+}
+
+impl Objective for Top {
+    type Value = Vec<Bridge>;
+
+    fn empty(&self) -> Vec<Bridge> {
+        Vec::new()
+    }
+
+    fn prepend(&self, comp: &Component, rest: &Vec<Bridge>) -> Vec<Bridge> {
+        let alone = Bridge::default().prepend(comp);
+
+        self.truncate(
+            std::iter::once(alone)
+                .chain(rest.iter().map(|bridge| bridge.prepend(comp)))
+                .collect(),
+        )
+    }
+
+    fn union(&self, mut a: Vec<Bridge>, b: Vec<Bridge>) -> Vec<Bridge> {
+        a.extend(b);
+        self.truncate(a)
+    }
+}
@@ -0,0 +1,295 @@
+use num_bigint::BigInt;
+use std::fmt::{Debug, Display};
+use std::marker::PhantomData;
+
+// The register machines are generic over the arithmetic of their registers, given by a
+// policy type implementing `Arithmetic`:
+// - Checked<T>: fixed-width integers, overflow is an error
+// - Wrapping<T>: fixed-width integers, overflow wraps around
+// - Saturating<T>: fixed-width integers, overflow clamps to the bounds
+// - Unbounded: arbitrary precision integers, never overflows
+// The operations return a `Fault` which the machines report as a `RuntimeError`.
+
+/// Arithmetic error raised by an operation on the registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Overflow,
+    DivisionByZero,
+    InvalidOffset, // value too large to be used as a jump offset
+}
+
+impl Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::Overflow => write!(f, "arithmetic overflow"),
+            Fault::DivisionByZero => write!(f, "division by zero"),
+            Fault::InvalidOffset => write!(f, "invalid jump offset"),
+        }
+    }
+}
+
+impl std::error::Error for Fault {}
+
+/// Arithmetic of the registers of a machine.
+pub trait Arithmetic {
+    type Word: Clone + Debug + Display + PartialEq + PartialOrd;
+
+    /// Converts an integer (e.g. a literal in the program) to a word.
+    fn word(int: i64) -> Result<Self::Word, Fault>;
+
+    /// Converts a word to an integer, for use as a jump offset.
+    fn int(word: &Self::Word) -> Result<i64, Fault>;
+
+    fn add(a: &Self::Word, b: &Self::Word) -> Result<Self::Word, Fault>;
+    fn sub(a: &Self::Word, b: &Self::Word) -> Result<Self::Word, Fault>;
+    fn mul(a: &Self::Word, b: &Self::Word) -> Result<Self::Word, Fault>;
+    fn rem(a: &Self::Word, b: &Self::Word) -> Result<Self::Word, Fault>;
+
+    fn zero() -> Self::Word {
+        Self::word(0).expect("zero is representable")
+    }
+}
+
+pub struct Checked<T>(PhantomData<T>);
+pub struct Wrapping<T>(PhantomData<T>);
+pub struct Saturating<T>(PhantomData<T>);
+pub struct Unbounded;
+
+macro_rules! impl_fixed_width {
+    ($($int:ty),*) => {$(
+        impl Arithmetic for Checked<$int> {
+            type Word = $int;
+
+            fn word(int: i64) -> Result<$int, Fault> {
+                match (<$int>::MIN as i64..=<$int>::MAX as i64).contains(&int) {
+                    true => Ok(int as $int),
+                    false => Err(Fault::Overflow),
+                }
+            }
+
+            fn int(word: &$int) -> Result<i64, Fault> {
+                Ok(*word as i64)
+            }
+
+            fn add(a: &$int, b: &$int) -> Result<$int, Fault> {
+                a.checked_add(*b).ok_or(Fault::Overflow)
+            }
+
+            fn sub(a: &$int, b: &$int) -> Result<$int, Fault> {
+                a.checked_sub(*b).ok_or(Fault::Overflow)
+            }
+
+            fn mul(a: &$int, b: &$int) -> Result<$int, Fault> {
+                a.checked_mul(*b).ok_or(Fault::Overflow)
+            }
+
+            fn rem(a: &$int, b: &$int) -> Result<$int, Fault> {
+                match *b {
+                    0 => Err(Fault::DivisionByZero),
+                    _ => a.checked_rem(*b).ok_or(Fault::Overflow),
+                }
+            }
+        }
+
+        impl Arithmetic for Wrapping<$int> {
+            type Word = $int;
+
+            fn word(int: i64) -> Result<$int, Fault> {
+                Ok(int as $int) // truncate
+            }
+
+            fn int(word: &$int) -> Result<i64, Fault> {
+                Ok(*word as i64)
+            }
+
+            fn add(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.wrapping_add(*b))
+            }
+
+            fn sub(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.wrapping_sub(*b))
+            }
+
+            fn mul(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.wrapping_mul(*b))
+            }
+
+            fn rem(a: &$int, b: &$int) -> Result<$int, Fault> {
+                match *b {
+                    0 => Err(Fault::DivisionByZero),
+                    _ => Ok(a.wrapping_rem(*b)),
+                }
+            }
+        }
+
+        impl Arithmetic for Saturating<$int> {
+            type Word = $int;
+
+            fn word(int: i64) -> Result<$int, Fault> {
+                Ok(int.clamp(<$int>::MIN as i64, <$int>::MAX as i64) as $int)
+            }
+
+            fn int(word: &$int) -> Result<i64, Fault> {
+                Ok(*word as i64)
+            }
+
+            fn add(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.saturating_add(*b))
+            }
+
+            fn sub(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.saturating_sub(*b))
+            }
+
+            fn mul(a: &$int, b: &$int) -> Result<$int, Fault> {
+                Ok(a.saturating_mul(*b))
+            }
+
+            fn rem(a: &$int, b: &$int) -> Result<$int, Fault> {
+                match *b {
+                    0 => Err(Fault::DivisionByZero),
+                    _ => Ok(a.wrapping_rem(*b)), // only MIN % -1 overflows, with result 0
+                }
+            }
+        }
+    )*};
+}
+
+impl_fixed_width!(i32, i64);
+
+impl Arithmetic for Unbounded {
+    type Word = BigInt;
+
+    fn word(int: i64) -> Result<BigInt, Fault> {
+        Ok(BigInt::from(int))
+    }
+
+    fn int(word: &BigInt) -> Result<i64, Fault> {
+        i64::try_from(word).map_err(|_| Fault::InvalidOffset)
+    }
+
+    fn add(a: &BigInt, b: &BigInt) -> Result<BigInt, Fault> {
+        Ok(a + b)
+    }
+
+    fn sub(a: &BigInt, b: &BigInt) -> Result<BigInt, Fault> {
+        Ok(a - b)
+    }
+
+    fn mul(a: &BigInt, b: &BigInt) -> Result<BigInt, Fault> {
+        Ok(a * b)
+    }
+
+    fn rem(a: &BigInt, b: &BigInt) -> Result<BigInt, Fault> {
+        match *b == BigInt::ZERO {
+            true => Err(Fault::DivisionByZero),
+            false => Ok(a % b),
+        }
+    }
+}
+
+/// Error raised while running a machine, with the index of the faulty command.
+#[derive(Debug)]
+pub struct RuntimeError<C> {
+    pub fault: Fault,
+    pub pc: usize,
+    pub cmd: C,
+}
+
+impl<C: Debug> Display for RuntimeError<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at index {} ({:?})", self.fault, self.pc, self.cmd)
+    }
+}
+
+impl<C: Debug> std::error::Error for RuntimeError<C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked() {
+        type A = Checked<i32>;
+        let (max, min) = (i32::MAX, i32::MIN);
+
+        assert_eq!(Ok(max), A::word(max as i64));
+        assert_eq!(Err(Fault::Overflow), A::word(max as i64 + 1));
+        assert_eq!(Err(Fault::Overflow), A::word(min as i64 - 1));
+
+        assert_eq!(Ok(max), A::add(&(max - 1), &1));
+        assert_eq!(Err(Fault::Overflow), A::add(&max, &1));
+        assert_eq!(Err(Fault::Overflow), A::sub(&min, &1));
+        assert_eq!(Err(Fault::Overflow), A::mul(&(max / 2 + 1), &2));
+        assert_eq!(Err(Fault::Overflow), A::rem(&min, &-1));
+        assert_eq!(Err(Fault::DivisionByZero), A::rem(&1, &0));
+
+        assert_eq!(Err(Fault::Overflow), Checked::<i64>::add(&i64::MAX, &1));
+    }
+
+    #[test]
+    fn wrapping() {
+        type A = Wrapping<i32>;
+        let (max, min) = (i32::MAX, i32::MIN);
+
+        assert_eq!(Ok(min), A::word(max as i64 + 1));
+        assert_eq!(Ok(min), A::add(&max, &1));
+        assert_eq!(Ok(max), A::sub(&min, &1));
+        assert_eq!(Ok(-2), A::mul(&max, &2));
+        assert_eq!(Ok(0), A::rem(&min, &-1));
+        assert_eq!(Err(Fault::DivisionByZero), A::rem(&1, &0));
+
+        assert_eq!(Ok(i64::MIN), Wrapping::<i64>::add(&i64::MAX, &1));
+    }
+
+    #[test]
+    fn saturating() {
+        type A = Saturating<i32>;
+        let (max, min) = (i32::MAX, i32::MIN);
+
+        assert_eq!(Ok(max), A::word(i64::MAX));
+        assert_eq!(Ok(min), A::word(i64::MIN));
+        assert_eq!(Ok(max), A::add(&max, &1));
+        assert_eq!(Ok(min), A::sub(&min, &1));
+        assert_eq!(Ok(max), A::mul(&max, &2));
+        assert_eq!(Ok(min), A::mul(&max, &-2));
+        assert_eq!(Ok(0), A::rem(&min, &-1));
+        assert_eq!(Err(Fault::DivisionByZero), A::rem(&1, &0));
+
+        assert_eq!(Ok(i64::MAX), Saturating::<i64>::add(&i64::MAX, &1));
+    }
+
+    #[test]
+    fn unbounded() {
+        type A = Unbounded;
+        let max = BigInt::from(i64::MAX);
+
+        assert_eq!(Ok(BigInt::from(i64::MIN)), A::word(i64::MIN));
+        assert_eq!(Ok(&max + 1), A::add(&max, &BigInt::from(1)));
+        let min = BigInt::from(i64::MIN);
+        assert_eq!(Ok(&min - 1), A::sub(&min, &BigInt::from(1)));
+        assert_eq!(Ok(&max * &max), A::mul(&max, &max));
+        assert_eq!(Err(Fault::DivisionByZero), A::rem(&max, &BigInt::from(0)));
+
+        assert_eq!(Ok(i64::MAX), A::int(&max));
+        assert_eq!(Err(Fault::InvalidOffset), A::int(&(&max + 1)));
+        assert_eq!(BigInt::from(0), A::zero());
+    }
+
+    #[test]
+    fn runtime_error() {
+        let fault = Checked::<i32>::add(&i32::MAX, &1).unwrap_err();
+        let error = RuntimeError {
+            fault,
+            pc: 3,
+            cmd: "inc a",
+        };
+
+        assert_eq!(Fault::Overflow, error.fault);
+        assert_eq!(
+            "arithmetic overflow at index 3 (\"inc a\")",
+            error.to_string()
+        );
+        assert_eq!("division by zero", Fault::DivisionByZero.to_string());
+    }
+}
@@ -0,0 +1,423 @@
+use std::fmt::Write;
+
+// The decompiler lifts a program of register machine commands to structured pseudocode.
+// Each command is first lifted to an `Op`, which is either a statement or a jump to an
+// absolute index. Backward jumps delimit the loops: the jump at index `latch` to the
+// index `head` repeats the commands in [head, latch]. Forward jumps become conditionals,
+// or break/continue/halt when they leave the enclosing loop or the program.
+// Whatever cannot be structured is kept as a `goto`.
+
+/// Condition of a jump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cond {
+    Always,
+    NonZero(String), // the operand is nonzero
+}
+
+impl Cond {
+    /// Render the condition, or its negation.
+    fn test(&self, negate: bool) -> String {
+        match (self, negate) {
+            (Cond::Always, false) => String::from("true"),
+            (Cond::Always, true) => String::from("false"),
+            (Cond::NonZero(x), false) => format!("{x} != 0"),
+            (Cond::NonZero(x), true) => format!("{x} == 0"),
+        }
+    }
+}
+
+/// Command lifted to the decompiler's representation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Nop,
+    Stmt(String),
+    Jump(Cond, i64), // absolute index of the target
+    Opaque(String),  // command that cannot be analysed statically, e.g. a computed jump
+}
+
+/// Commands which can be lifted by the decompiler.
+pub trait Lift {
+    /// Lift the command at index `idx` of the program.
+    fn lift(&self, idx: usize) -> Op;
+}
+
+/// Node of the structured program. Each node carries the index of its command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node {
+    Stmt(usize, String),
+    If {
+        idx: usize,
+        cond: String,
+        then: Vec<Node>,
+        els: Vec<Node>,
+    },
+    Loop {
+        latch: usize,
+        cond: Option<String>, // `None` for an unconditional loop
+        body: Vec<Node>,
+    },
+    Break(usize, usize), // (index, number of loops exited)
+    Continue(usize),
+    Goto(usize, usize), // (index, target)
+    Halt(usize),
+}
+
+/// Loop of the program, repeating the commands in [head, latch].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Loop {
+    pub head: usize,
+    pub latch: usize,
+    pub height: usize, // 1 for an innermost loop, 1 + the height of the loops inside otherwise
+}
+
+impl Loop {
+    fn contains(&self, other: &Loop) -> bool {
+        self != other && self.head <= other.head && other.latch <= self.latch
+    }
+}
+
+/// Loop enclosing the block being structured: (head, latch, unconditional).
+type Enclosing = (usize, usize, bool);
+
+pub struct Decompiler {
+    ops: Vec<Op>,
+}
+
+impl Decompiler {
+    pub fn new<C: Lift>(cmds: &[C]) -> Self {
+        let ops = cmds
+            .iter()
+            .enumerate()
+            .map(|(idx, cmd)| cmd.lift(idx))
+            .collect();
+
+        Self { ops }
+    }
+
+    /// Return the target of the jump at index `idx` if it jumps backward (or in place).
+    fn back_edge(&self, idx: usize) -> Option<usize> {
+        match self.ops[idx] {
+            Op::Jump(_, target) if 0 <= target && target <= idx as i64 => Some(target as usize),
+            _ => None,
+        }
+    }
+
+    /// Every loop of the program, ordered by height and then by position.
+    pub fn loops(&self) -> Vec<Loop> {
+        let mut loops: Vec<Loop> = (0..self.ops.len())
+            .filter_map(|latch| {
+                let head = self.back_edge(latch)?;
+                Some(Loop {
+                    head,
+                    latch,
+                    height: 1,
+                })
+            })
+            .collect();
+
+        // a loop only contains shorter loops, so process them by increasing length
+        loops.sort_by_key(|lp| lp.latch - lp.head);
+
+        for k in 0..loops.len() {
+            loops[k].height = 1 + loops[..k]
+                .iter()
+                .filter(|inner| loops[k].contains(inner))
+                .map(|inner| inner.height)
+                .max()
+                .unwrap_or(0);
+        }
+
+        loops.sort_by_key(|lp| (lp.height, lp.head, lp.latch));
+
+        loops
+    }
+
+    /// Render the loop-nesting table in markdown: a row per command and a column per
+    /// height of loop, with the number of the loops containing the command.
+    pub fn loop_table(&self) -> String {
+        let loops = self.loops();
+        let columns = loops.iter().map(|lp| lp.height).max().unwrap_or(0);
+
+        let mut table = String::from("cmd |");
+        table.push_str(&" loop  |".repeat(columns));
+        table.push_str("\n----|");
+        table.push_str(&"-------|".repeat(columns));
+        table.push('\n');
+
+        for idx in 0..self.ops.len() {
+            write!(table, "{idx:<4}|").unwrap();
+
+            for height in 1..=columns {
+                let number = loops
+                    .iter()
+                    .position(|lp| lp.height == height && lp.head <= idx && idx <= lp.latch);
+
+                match number {
+                    Some(number) => write!(table, "{:^7}|", number + 1).unwrap(),
+                    None => table.push_str("       |"),
+                }
+            }
+
+            table.push('\n');
+        }
+
+        table
+    }
+
+    /// Recover the loops and conditionals of the program.
+    pub fn structure(&self) -> Vec<Node> {
+        self.block(0, self.ops.len(), &mut Vec::new())
+    }
+
+    /// Structure the commands in [lo, hi), inside the `enclosing` loops (innermost last).
+    fn block(&self, lo: usize, hi: usize, enclosing: &mut Vec<Enclosing>) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut idx = lo;
+
+        while idx < hi {
+            // the outermost loop starting here which fits in the block
+            if let Some(latch) = (idx..hi).rev().find(|&j| self.back_edge(j) == Some(idx)) {
+                let Op::Jump(cond, _) = &self.ops[latch] else {
+                    unreachable!()
+                };
+
+                enclosing.push((idx, latch, *cond == Cond::Always));
+                let body = self.block(idx, latch, enclosing);
+                enclosing.pop();
+
+                let cond = match cond {
+                    Cond::Always => None,
+                    cond => Some(cond.test(false)),
+                };
+
+                nodes.push(Node::Loop { latch, cond, body });
+                idx = latch + 1;
+                continue;
+            }
+
+            match &self.ops[idx] {
+                Op::Nop => {}
+                Op::Stmt(text) => nodes.push(Node::Stmt(idx, text.clone())),
+                Op::Opaque(text) => nodes.push(Node::Stmt(idx, format!("{text}  // not lifted"))),
+                Op::Jump(_, target) if *target == idx as i64 + 1 => {}
+                Op::Jump(cond @ Cond::NonZero(_), target)
+                    if idx as i64 + 1 < *target && *target <= hi as i64 =>
+                {
+                    // skip over [idx + 1, target) when the condition holds
+                    let target = *target as usize;
+                    let (then, els, next) = match self.forward_jump(target - 1) {
+                        // the skipped commands end with a jump over [target, end): if/else
+                        Some(end) if target - 1 > idx && target < end && end <= hi => (
+                            self.block(idx + 1, target - 1, enclosing),
+                            self.block(target, end, enclosing),
+                            end,
+                        ),
+                        _ => (self.block(idx + 1, target, enclosing), Vec::new(), target),
+                    };
+
+                    let node = match then.is_empty() {
+                        true => Node::If {
+                            idx,
+                            cond: cond.test(false),
+                            then: els,
+                            els: Vec::new(),
+                        },
+                        false => Node::If {
+                            idx,
+                            cond: cond.test(true),
+                            then,
+                            els,
+                        },
+                    };
+
+                    nodes.push(node);
+                    idx = next;
+                    continue;
+                }
+                Op::Jump(Cond::Always, target) => nodes.push(self.jump(idx, *target, enclosing)),
+                Op::Jump(cond, target) => nodes.push(Node::If {
+                    idx,
+                    cond: cond.test(false),
+                    then: vec![self.jump(idx, *target, enclosing)],
+                    els: Vec::new(),
+                }),
+            }
+
+            idx += 1;
+        }
+
+        nodes
+    }
+
+    /// Return the target of the command at index `idx` if it jumps forward unconditionally.
+    fn forward_jump(&self, idx: usize) -> Option<usize> {
+        match self.ops[idx] {
+            Op::Jump(Cond::Always, target) if target > idx as i64 => Some(target as usize),
+            _ => None,
+        }
+    }
+
+    /// Translate the jump at index `idx` which could not be structured.
+    fn jump(&self, idx: usize, target: i64, enclosing: &[Enclosing]) -> Node {
+        if target < 0 || target >= self.ops.len() as i64 {
+            return Node::Halt(idx);
+        }
+
+        let target = target as usize;
+
+        // exit from any of the enclosing loops
+        if let Some(levels) = enclosing.iter().rev().position(|lp| target == lp.1 + 1) {
+            return Node::Break(idx, levels + 1);
+        }
+
+        match enclosing.last() {
+            Some(&(_, latch, _)) if target == latch => Node::Continue(idx),
+            Some(&(head, _, true)) if target == head => Node::Continue(idx),
+            _ => Node::Goto(idx, target),
+        }
+    }
+
+    /// Render the structured program, with the index of the commands in the margin.
+    pub fn pseudocode(&self) -> String {
+        let mut code = String::new();
+        render(&self.structure(), 0, &mut code);
+
+        code
+    }
+}
+
+fn line(code: &mut String, idx: Option<usize>, depth: usize, text: &str) {
+    match idx {
+        Some(idx) => write!(code, "{idx:>3} | ").unwrap(),
+        None => code.push_str("    | "),
+    }
+
+    writeln!(code, "{}{text}", "    ".repeat(depth)).unwrap();
+}
+
+fn render(nodes: &[Node], depth: usize, code: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Stmt(idx, text) => line(code, Some(*idx), depth, text),
+            Node::If {
+                idx,
+                cond,
+                then,
+                els,
+            } => {
+                line(code, Some(*idx), depth, &format!("if {cond} {{"));
+                render(then, depth + 1, code);
+
+                if !els.is_empty() {
+                    line(code, None, depth, "} else {");
+                    render(els, depth + 1, code);
+                }
+
+                line(code, None, depth, "}");
+            }
+            Node::Loop { latch, cond, body } => {
+                match cond {
+                    Some(_) => line(code, None, depth, "do {"),
+                    None => line(code, None, depth, "loop {"),
+                }
+
+                render(body, depth + 1, code);
+
+                match cond {
+                    Some(cond) => line(code, Some(*latch), depth, &format!("}} while {cond}")),
+                    None => line(code, Some(*latch), depth, "}"),
+                }
+            }
+            Node::Break(idx, 1) => line(code, Some(*idx), depth, "break"),
+            Node::Break(idx, levels) => line(code, Some(*idx), depth, &format!("break {levels}")),
+            Node::Continue(idx) => line(code, Some(*idx), depth, "continue"),
+            Node::Goto(idx, target) => line(code, Some(*idx), depth, &format!("goto {target}")),
+            Node::Halt(idx) => line(code, Some(*idx), depth, "halt"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assembunny command, only lifting the jumps with a constant offset.
+    struct Cmd<'a>(&'a str);
+
+    impl Lift for Cmd<'_> {
+        fn lift(&self, idx: usize) -> Op {
+            match self.0.split_whitespace().collect::<Vec<_>>()[..] {
+                ["jnz", "0", _] => Op::Nop,
+                ["jnz", x, jmp] => {
+                    let cond = match x.parse::<i64>() {
+                        Ok(_) => Cond::Always,
+                        Err(_) => Cond::NonZero(x.to_string()),
+                    };
+
+                    Op::Jump(cond, idx as i64 + jmp.parse::<i64>().unwrap())
+                }
+                _ => Op::Stmt(self.0.to_string()),
+            }
+        }
+    }
+
+    fn decompile(program: &str) -> Decompiler {
+        let cmds: Vec<Cmd> = program.lines().map(Cmd).collect();
+
+        Decompiler::new(&cmds)
+    }
+
+    // halve a repeatedly, transmitting c = 2 - a % 2 each time
+    const HALVE: &str = "cpy a b\ncpy 0 a\ncpy 2 c\njnz b 2\njnz 1 6\ndec b\ndec c\n\
+                         jnz c -4\ninc a\njnz 1 -7\nout c\njnz a -11";
+
+    #[test]
+    fn loops() {
+        let loops: Vec<(usize, usize, usize)> = decompile(HALVE)
+            .loops()
+            .iter()
+            .map(|lp| (lp.head, lp.latch, lp.height))
+            .collect();
+
+        assert_eq!(vec![(3, 7, 1), (2, 9, 2), (0, 11, 3)], loops);
+    }
+
+    #[test]
+    fn pseudocode() {
+        let expected = "    | do {
+  0 |     cpy a b
+  1 |     cpy 0 a
+    |     loop {
+  2 |         cpy 2 c
+    |         do {
+  3 |             if b == 0 {
+  4 |                 break 2
+    |             }
+  5 |             dec b
+  6 |             dec c
+  7 |         } while c != 0
+  8 |         inc a
+  9 |     }
+ 10 |     out c
+ 11 | } while a != 0
+";
+        assert_eq!(expected, decompile(HALVE).pseudocode());
+    }
+
+    // the program of 2016 day 25, whose loops are analysed in aoc-2016/day-25/main.md
+    const CLOCK: &str = "cpy a d\ncpy 7 c\ncpy 365 b\ninc d\ndec b\njnz b -2\ndec c\njnz c -5\n\
+                         cpy d a\njnz 0 0\ncpy a b\ncpy 0 a\ncpy 2 c\njnz b 2\njnz 1 6\ndec b\n\
+                         dec c\njnz c -4\ninc a\njnz 1 -7\ncpy 2 b\njnz c 2\njnz 1 4\ndec b\n\
+                         dec c\njnz 1 -4\njnz 0 0\nout b\njnz a -19\njnz 1 -21";
+
+    #[test]
+    fn loop_table_of_main_md() {
+        let main_md = include_str!("../../../aoc-2016/day-25/main.md");
+
+        // the table runs from its header to the first blank line
+        let start = main_md.find("cmd |").unwrap();
+        let end = start + main_md[start..].find("\n\n").unwrap() + 1;
+
+        assert_eq!(&main_md[start..end], decompile(CLOCK).loop_table());
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+use std::io;
+use std::thread;
+
+// The knot hash ties knots in a circular list of the 256 bytes: each length reverses the
+// range of that length at the current position, which then moves by the length plus a
+// skip size growing by one after each length. The full hash runs 64 rounds of the input
+// bytes followed by a fixed suffix, and XORs each block of 16 bytes of the list.
+//
+// The list has 256 elements, so the positions are bytes and wrap around for free.
+
+const SUFFIX: [u8; 5] = [17, 31, 73, 47, 23];
+const ROUNDS: usize = 64;
+
+/// State of the circular list, for tying knots one length at a time.
+#[derive(Clone)]
+pub struct KnotHash {
+    list: [u8; 256],
+    position: u8,
+    skip_size: u8, // only matters modulo 256
+}
+
+impl Default for KnotHash {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KnotHash {
+    pub fn new() -> Self {
+        Self {
+            list: core::array::from_fn(|x| x as u8),
+            position: 0,
+            skip_size: 0,
+        }
+    }
+
+    pub fn list(&self) -> &[u8; 256] {
+        &self.list
+    }
+
+    /// Reverses the range of length `length` starting at the current position,
+    /// which wraps to the beginning of the list if needed, and moves the position.
+    ///
+    /// The range is reversed in place, by swapping its ends towards its middle.
+    pub fn reverse(&mut self, length: usize) {
+        assert!(length <= self.list.len(), "length {} is too long", length);
+
+        if length > 1 {
+            let mut low = self.position;
+            let mut high = self.position.wrapping_add((length - 1) as u8);
+
+            for _ in 0..length / 2 {
+                self.list.swap(low as usize, high as usize);
+                low = low.wrapping_add(1);
+                high = high.wrapping_sub(1);
+            }
+        }
+
+        // the length is at most 256, so it can be added modulo 256
+        self.position = self
+            .position
+            .wrapping_add(length as u8)
+            .wrapping_add(self.skip_size);
+        self.skip_size = self.skip_size.wrapping_add(1);
+    }
+
+    /// Computes the dense hash, using the current list as sparse hash.
+    pub fn dense_hash(&self) -> Digest {
+        let mut hash = [0; 16];
+
+        for (byte, block) in hash.iter_mut().zip(self.list.chunks_exact(16)) {
+            *byte = block.iter().fold(0, |acc, val| acc ^ val);
+        }
+
+        Digest(hash)
+    }
+}
+
+/// Knot hash of an input given in pieces, like the `std::hash::Hasher`s.
+///
+/// The rounds go over the whole input, so it is kept until the digest is computed.
+#[derive(Clone, Default)]
+pub struct Hasher {
+    input: Vec<u8>,
+}
+
+impl Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    pub fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+    }
+
+    /// Computes the hash of everything written so far.
+    pub fn digest(&self) -> Digest {
+        let mut hash = KnotHash::new();
+
+        for _ in 0..ROUNDS {
+            for &length in self.input.iter().chain(SUFFIX.iter()) {
+                hash.reverse(length as usize);
+            }
+        }
+
+        hash.dense_hash()
+    }
+}
+
+/// Allows to write formatted input with `write!`.
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Hasher::write(self, buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Dense hash of 16 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn bytes(&self) -> [u8; 16] {
+        self.0
+    }
+
+    /// Returns the hash as an integer, whose most significant bit is the first bit.
+    pub fn to_u128(&self) -> u128 {
+        u128::from_be_bytes(self.0)
+    }
+
+    /// Returns the hash as a string of 32 hex digits.
+    pub fn hex(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the 128 bits of the hash, most significant bit of the first byte first.
+    pub fn bits(&self) -> impl Iterator<Item = bool> {
+        let hash = self.to_u128();
+        (0..128).rev().map(move |idx| hash >> idx & 1 == 1)
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Hashes the input using the knot hash algorithm.
+pub fn hash(input: &[u8]) -> Digest {
+    let mut hasher = Hasher::new();
+    hasher.write(input);
+    hasher.digest()
+}
+
+/// Hashes each input, spreading the inputs over the available threads.
+/// The digests are in the order of the inputs.
+pub fn hash_batch<T: AsRef<[u8]> + Sync>(inputs: &[T]) -> Vec<Digest> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = inputs.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = inputs
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|input| hash(input.as_ref()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn reverse() {
+        let mut hash = KnotHash::new();
+
+        hash.reverse(3);
+        hash.reverse(4);
+        assert_eq!([2, 1, 0, 6, 5, 4, 3, 7], hash.list()[..8]);
+
+        // the second range wraps around the end of the list
+        let mut hash = KnotHash::new();
+
+        hash.reverse(254);
+        hash.reverse(4);
+        assert_eq!([255, 254, 251], hash.list()[..3]);
+        assert_eq!([0, 252, 253], hash.list()[253..]);
+    }
+
+    #[test]
+    fn hash_examples() {
+        let examples = [
+            ("", "a2582a3a0e66e6e86e3812dcb672a272"),
+            ("AoC 2017", "33efeb34ea91902bb2f59c9920caa6cd"),
+            ("1,2,3", "3efbe78a8d82f29979031a4aa0b16a9d"),
+            ("1,2,4", "63960835bcdc130f0b66d7ff4f6a5a8e"),
+        ];
+
+        for (input, expected) in examples {
+            assert_eq!(expected, hash(input.as_bytes()).hex());
+        }
+    }
+
+    #[test]
+    fn streaming() {
+        let mut hasher = Hasher::new();
+
+        hasher.write_str("AoC");
+        write!(hasher, " {}", 2017).unwrap();
+
+        assert_eq!(hash(b"AoC 2017"), hasher.digest());
+    }
+
+    #[test]
+    fn bits() {
+        let mut bytes = [0; 16];
+        bytes[0] = 0b1010_0000;
+        bytes[15] = 1;
+
+        let bits: Vec<bool> = Digest(bytes).bits().collect();
+
+        assert_eq!(128, bits.len());
+        assert_eq!([true, false, true, false], bits[..4]);
+        assert!(bits[127]);
+        assert_eq!(3, bits.iter().filter(|&&bit| bit).count());
+    }
+
+    #[test]
+    fn hash_batch() {
+        let inputs: Vec<String> = (0..50).map(|idx| format!("flqrgnkx-{idx}")).collect();
+        let expected: Vec<Digest> = inputs.iter().map(|input| hash(input.as_bytes())).collect();
+
+        assert_eq!(expected, super::hash_batch(&inputs));
+        assert!(super::hash_batch::<&str>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+pub use num_complex::Complex;
+
+// The walker only deals with unit steps on the integer lattice, so that it can record
+// walks of any origin (turn-and-walk instructions, spirals, tubes, hex grids in axial
+// coordinates...) as long as they are expressed as a sequence of steps.
+
+pub type Point = Complex<i32>;
+
+/// Record of a walk on the integer lattice, one lattice point per step.
+pub struct Walker {
+    path: Vec<Point>,                   // path[idx] = position after `idx` steps
+    visits: HashMap<Point, Vec<usize>>, // step indices at which each point was visited
+    revisits: Vec<usize>,               // step indices landing on an already visited point
+}
+
+impl Walker {
+    pub fn new(start: Point) -> Self {
+        Self {
+            path: vec![start],
+            visits: HashMap::from([(start, vec![0])]),
+            revisits: Vec::new(),
+        }
+    }
+
+    /// Current position of the walker.
+    pub fn position(&self) -> Point {
+        *self
+            .path
+            .last()
+            .expect("path contains the starting position")
+    }
+
+    /// Every visited point, indexed by step.
+    pub fn path(&self) -> &[Point] {
+        &self.path
+    }
+
+    /// Take a single step in direction `dir` and record the destination.
+    pub fn step(&mut self, dir: Point) {
+        let pos = self.position() + dir;
+        let idx = self.path.len();
+
+        let visits = self.visits.entry(pos).or_default();
+
+        if !visits.is_empty() {
+            self.revisits.push(idx);
+        }
+
+        visits.push(idx);
+        self.path.push(pos);
+    }
+
+    /// Take `len` steps in direction `dir`, recording every point on the way.
+    pub fn walk(&mut self, dir: Point, len: u32) {
+        for _ in 0..len {
+            self.step(dir);
+        }
+    }
+
+    /// Step indices at which the point `pos` was visited.
+    pub fn visits(&self, pos: Point) -> &[usize] {
+        self.visits.get(&pos).map_or(&[], |visits| visits)
+    }
+
+    /// First point visited twice.
+    pub fn first_revisit(&self) -> Option<Point> {
+        self.nth_revisit(0)
+    }
+
+    /// Point of the `n`-th step (starting at 0) landing on an already visited point.
+    pub fn nth_revisit(&self, n: usize) -> Option<Point> {
+        self.revisits.get(n).map(|&idx| self.path[idx])
+    }
+
+    /// All self-intersections of the path, i.e. the points visited more than once.
+    /// The points are ordered by the step at which they are visited for the second time.
+    pub fn intersections(&self) -> Vec<Point> {
+        let mut points: Vec<Point> = Vec::new();
+
+        for &idx in self.revisits.iter() {
+            let pos = self.path[idx];
+
+            // only keep the step of the second visit of each point
+            if self.visits[&pos][1] == idx {
+                points.push(pos);
+            }
+        }
+
+        points
+    }
+
+    /// Smallest box containing the path, as its (bottom-left, top-right) corners.
+    pub fn bounding_box(&self) -> (Point, Point) {
+        let init = (self.path[0], self.path[0]);
+
+        self.path.iter().fold(init, |(min, max), pos| {
+            let min = Complex::new(min.re.min(pos.re), min.im.min(pos.im));
+            let max = Complex::new(max.re.max(pos.re), max.im.max(pos.im));
+            (min, max)
+        })
+    }
+
+    /// First point of the path with the largest taxicab distance to the starting point,
+    /// together with its step index.
+    pub fn farthest(&self) -> (usize, Point) {
+        let start = self.path[0];
+
+        self.path
+            .iter()
+            .copied()
+            .enumerate()
+            .rev() // max_by_key returns the last maximum
+            .max_by_key(|(_, pos)| (pos - start).l1_norm())
+            .expect("path contains the starting position")
+    }
+
+    /// Render the path as ASCII art, with the imaginary axis pointing up.
+    /// The start and end are marked `S` and `E`, and points visited more than once `X`.
+    pub fn render_ascii(&self) -> String {
+        let (min, max) = self.bounding_box();
+        let start = self.path[0];
+        let end = self.position();
+
+        let mut render = String::new();
+
+        for im in (min.im..=max.im).rev() {
+            for re in min.re..=max.re {
+                let pos = Complex::new(re, im);
+
+                let ch = match self.visits(pos).len() {
+                    _ if pos == start => 'S',
+                    _ if pos == end => 'E',
+                    0 => '.',
+                    1 => '#',
+                    _ => 'X',
+                };
+
+                render.push(ch);
+            }
+
+            render.push('\n');
+        }
+
+        render
+    }
+
+    /// Render the path as an SVG polyline, with the imaginary axis pointing up.
+    pub fn render_svg(&self) -> String {
+        let (min, max) = self.bounding_box();
+        let (width, height) = (max.re - min.re + 2, max.im - min.im + 2);
+
+        // shift the points so that the path has a margin of one unit in the view box
+        let points: Vec<String> = self
+            .path
+            .iter()
+            .map(|pos| format!("{},{}", pos.re - min.re + 1, max.im - pos.im + 1))
+            .collect();
+
+        let mut svg = String::new();
+
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {width} {height}\">"
+        )
+        .unwrap();
+        writeln!(
+            svg,
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"0.2\"/>",
+            points.join(" ")
+        )
+        .unwrap();
+        svg.push_str("</svg>\n");
+
+        svg
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NORTH: Point = Complex::new(0, 1);
+    const EAST: Point = Complex::new(1, 0);
+    const SOUTH: Point = Complex::new(0, -1);
+    const WEST: Point = Complex::new(-1, 0);
+
+    /// The walk R8, R4, R4, R8 from the puzzle, starting north.
+    fn square_walk() -> Walker {
+        let mut walker = Walker::new(Complex::new(0, 0));
+
+        walker.walk(EAST, 8);
+        walker.walk(SOUTH, 4);
+        walker.walk(WEST, 4);
+        walker.walk(NORTH, 8);
+
+        walker
+    }
+
+    /// Back and forth on a line: 2 steps east, 2 west, 1 east.
+    fn shuttle_walk() -> Walker {
+        let mut walker = Walker::new(Complex::new(0, 0));
+
+        walker.walk(EAST, 2);
+        walker.walk(WEST, 2);
+        walker.walk(EAST, 1);
+
+        walker
+    }
+
+    #[test]
+    fn revisits() {
+        let walker = square_walk();
+
+        assert_eq!(walker.first_revisit(), Some(Complex::new(4, 0)));
+        assert_eq!(walker.nth_revisit(1), None);
+
+        let walker = shuttle_walk();
+
+        assert_eq!(walker.nth_revisit(0), Some(Complex::new(1, 0)));
+        assert_eq!(walker.nth_revisit(1), Some(Complex::new(0, 0)));
+        assert_eq!(walker.nth_revisit(2), Some(Complex::new(1, 0)));
+        assert_eq!(walker.nth_revisit(3), None);
+        assert_eq!(walker.visits(Complex::new(1, 0)), &[1, 3, 5]);
+    }
+
+    #[test]
+    fn intersections() {
+        assert_eq!(square_walk().intersections(), vec![Complex::new(4, 0)]);
+
+        // the third visit of (1, 0) is not a new intersection
+        assert_eq!(
+            shuttle_walk().intersections(),
+            vec![Complex::new(1, 0), Complex::new(0, 0)]
+        );
+    }
+
+    #[test]
+    fn bounding_box() {
+        assert_eq!(
+            square_walk().bounding_box(),
+            (Complex::new(0, -4), Complex::new(8, 4))
+        );
+        assert_eq!(
+            shuttle_walk().bounding_box(),
+            (Complex::new(0, 0), Complex::new(2, 0))
+        );
+    }
+
+    #[test]
+    fn farthest() {
+        assert_eq!(square_walk().farthest(), (12, Complex::new(8, -4)));
+
+        // the first of the points at the largest distance
+        assert_eq!(shuttle_walk().farthest(), (2, Complex::new(2, 0)));
+    }
+
+    #[test]
+    fn render_ascii() {
+        assert_eq!(shuttle_walk().render_ascii(), "SE#\n");
+    }
+}